@@ -0,0 +1,90 @@
+use std::{collections::HashSet, thread, time::Duration};
+
+use crate::{
+    config::{AppConfig, CaptureBackend},
+    evdev::EvdevCapture,
+    patterns::PointerGesture,
+    recognizer::Point,
+    wayland::{CancelReason, GestureCapture, LivePreview, StrokeOrigin},
+};
+
+/// Gesture capture by selected backend, evdev one has no overlay, so feedback is dropped
+pub enum GestureSource {
+    Wayland(Box<GestureCapture>),
+    Evdev(EvdevCapture),
+}
+
+impl GestureSource {
+    pub fn new(config: &AppConfig) -> Result<Self, ()> {
+        let source = match config.capture.backend {
+            CaptureBackend::Wayland => {
+                let capture = GestureCapture::new(&config.capture, &config.overlay)?;
+                GestureSource::Wayland(Box::new(capture))
+            }
+            CaptureBackend::Evdev => GestureSource::Evdev(EvdevCapture::new(&config.capture)?),
+        };
+
+        Ok(source)
+    }
+
+    pub fn next_gesture(&mut self) -> Option<Vec<Point>> {
+        match self {
+            GestureSource::Wayland(capture) => capture.next_gesture(),
+            GestureSource::Evdev(capture) => capture.next_gesture(),
+        }
+    }
+
+    pub fn cancel_reason(&self) -> Option<CancelReason> {
+        match self {
+            GestureSource::Wayland(capture) => capture.cancel_reason(),
+            GestureSource::Evdev(capture) => capture.cancel_reason(),
+        }
+    }
+
+    pub fn pointer_gesture(&self) -> Option<PointerGesture> {
+        match self {
+            GestureSource::Wayland(capture) => capture.pointer_gesture(),
+            GestureSource::Evdev(capture) => capture.pointer_gesture(),
+        }
+    }
+
+    pub fn set_pointer_gestures(&mut self, gestures: HashSet<PointerGesture>) {
+        match self {
+            GestureSource::Wayland(capture) => capture.set_pointer_gestures(gestures),
+            GestureSource::Evdev(capture) => capture.set_pointer_gestures(gestures),
+        }
+    }
+
+    /// Evdev paths are relative to the start point, so output is unknown
+    pub fn origin(&self) -> Option<&StrokeOrigin> {
+        match self {
+            GestureSource::Wayland(capture) => capture.origin(),
+            GestureSource::Evdev(_) => None,
+        }
+    }
+
+    pub fn set_counter(&mut self, captured: usize, total: usize) {
+        if let GestureSource::Wayland(capture) = self {
+            capture.set_counter(captured, total);
+        }
+    }
+
+    pub fn show_toast(&mut self, text: &str, position: Point) {
+        if let GestureSource::Wayland(capture) = self {
+            capture.show_toast(text, position);
+        }
+    }
+
+    pub fn set_preview(&mut self, preview: LivePreview) {
+        if let GestureSource::Wayland(capture) = self {
+            capture.set_preview(preview);
+        }
+    }
+
+    pub fn close_after(self, duration: Duration) -> thread::JoinHandle<()> {
+        match self {
+            GestureSource::Wayland(capture) => capture.close_after(duration),
+            GestureSource::Evdev(_) => thread::spawn(|| {}),
+        }
+    }
+}
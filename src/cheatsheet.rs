@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+
+use crate::{
+    config::{AppConfig, CaptureConfig, CommandLine, OverlayConfig},
+    draw::{Canvas, GRAY, SHADE, WHITE},
+    font::{text_size, GLYPH_ADVANCE},
+    patterns::{gesture_file_path, load_gesture, PointerGesture},
+    recognizer::Point,
+    wayland::GestureCapture,
+};
+
+const MARGIN: i32 = 32;
+const PADDING: i32 = 12;
+const CELL_WIDTH: i32 = 200;
+const THUMBNAIL_SIZE: i32 = 96;
+const TEXT_SCALE: i32 = 2;
+const LINE_HEIGHT: i32 = 18;
+const CELL_HEIGHT: i32 = PADDING + THUMBNAIL_SIZE + PADDING + 2 * LINE_HEIGHT + PADDING;
+
+/// Thumbnail of pattern with its command
+pub struct CheatsheetEntry {
+    pub name: String,
+    pub command: String,
+    pub path: Vec<Point>,
+}
+
+/// Show configured patterns on the overlay until click or Escape
+pub fn show_cheatsheet(config: &AppConfig) -> Result<(), ()> {
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+
+    // same as in recognition, only the first binding of pattern is used
+    for cmd in &config.commands {
+        if !seen.insert(cmd.pattern.as_str()) {
+            continue;
+        }
+
+        // wheel and rocker gestures have no path, only label is shown
+        if PointerGesture::from_name(&cmd.pattern).is_some() {
+            entries.push(CheatsheetEntry {
+                name: cmd.pattern.clone(),
+                command: command_label(&cmd.command),
+                path: Vec::new(),
+            });
+            continue;
+        }
+
+        let file_path = gesture_file_path(&cmd.pattern)?;
+        if !file_path.is_file() {
+            eprintln!("WARNING: pattern {} is not recorded, skipped", cmd.pattern);
+            continue;
+        }
+
+        let samples = match load_gesture(&file_path) {
+            Ok(samples) => samples,
+            Err(reason) => {
+                eprintln!("WARNING: pattern {} skipped, {}", cmd.pattern, reason);
+                continue;
+            }
+        };
+        let Some(path) = samples.into_iter().next() else {
+            continue;
+        };
+
+        entries.push(CheatsheetEntry {
+            name: cmd.pattern.clone(),
+            command: command_label(&cmd.command),
+            path,
+        });
+    }
+
+    if entries.is_empty() {
+        eprintln!("ERROR: there are no recorded patterns bound to commands");
+        return Err(());
+    }
+
+    // configured triggers and overlay are meant for drawing, cheatsheet is always
+    // visible and closed by click or Escape
+    let mut capture = GestureCapture::new(&CaptureConfig::default(), &OverlayConfig::default())?;
+    capture.set_cheatsheet(entries);
+    capture.next_gesture();
+
+    Ok(())
+}
+
+/// Grid of pattern thumbnails in the center of the output
+pub fn draw_cheatsheet(canvas: &mut Canvas, entries: &[CheatsheetEntry]) {
+    let count = entries.len() as i32;
+    let columns = ((canvas.width() - 2 * MARGIN) / CELL_WIDTH).clamp(1, count.max(1));
+    let rows = (count + columns - 1) / columns;
+
+    let panel_width = columns * CELL_WIDTH;
+    let panel_height = rows * CELL_HEIGHT;
+    let panel_x = (canvas.width() - panel_width) / 2;
+    let panel_y = ((canvas.height() - panel_height) / 2).max(0);
+
+    canvas.fill_rect(panel_x, panel_y, panel_width, panel_height, SHADE);
+
+    let max_chars = ((CELL_WIDTH - 2 * PADDING) / (GLYPH_ADVANCE * TEXT_SCALE)) as usize;
+    for (index, entry) in entries.iter().enumerate() {
+        let cell_x = panel_x + (index as i32 % columns) * CELL_WIDTH;
+        let cell_y = panel_y + (index as i32 / columns) * CELL_HEIGHT;
+
+        canvas.draw_pattern(
+            &entry.path,
+            cell_x + (CELL_WIDTH - THUMBNAIL_SIZE) / 2,
+            cell_y + PADDING,
+            THUMBNAIL_SIZE,
+            WHITE,
+        );
+
+        let text_y = cell_y + PADDING + THUMBNAIL_SIZE + PADDING;
+        let lines = [(&entry.name, WHITE), (&entry.command, GRAY)];
+        for (line, (text, color)) in lines.into_iter().enumerate() {
+            let text = truncate(text, max_chars);
+            let (text_width, _) = text_size(&text, TEXT_SCALE);
+            canvas.draw_text(
+                cell_x + (CELL_WIDTH - text_width) / 2,
+                text_y + line as i32 * LINE_HEIGHT,
+                TEXT_SCALE,
+                &text,
+                color,
+            );
+        }
+    }
+}
+
+fn command_label(command: &CommandLine) -> String {
+    match command {
+        CommandLine::Shell(raw_command) => raw_command.clone(),
+        CommandLine::Argv(argv) => argv.join(" "),
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let mut truncated = text
+        .chars()
+        .take(max_chars.saturating_sub(3))
+        .collect::<String>();
+    truncated.push_str("...");
+    truncated
+}
@@ -1,4 +1,9 @@
-use std::{fs::File, io::Read, path::Path};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 use serde_inline_default::serde_inline_default;
@@ -11,6 +16,18 @@ pub struct AppConfig {
     /// Patterns that associated with commands (Pattern => Command)
     #[serde(default)]
     pub commands: Vec<GestureCommand>,
+    /// Shell used to run string commands. By default $SHELL or sh
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// Desktop notifications about recognition results
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    /// Feedback drawn on the gesture overlay
+    #[serde(default)]
+    pub overlay: OverlayConfig,
+    /// How gesture capture is finished
+    #[serde(default)]
+    pub capture: CaptureConfig,
 }
 
 #[serde_inline_default]
@@ -40,10 +57,359 @@ pub struct RecognizerConfig {
     pub height: f64,
 }
 
+#[serde_inline_default]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    /// Send notifications through org.freedesktop.Notifications
+    #[serde_inline_default(false)]
+    pub enabled: bool,
+    /// Notify about recognized gestures, not only about rejected ones and failed commands
+    #[serde_inline_default(true)]
+    pub on_success: bool,
+    /// Time in milliseconds after which notification disappears (-1 server default)
+    #[serde_inline_default(3000)]
+    pub expire_timeout: i32,
+    /// Icon name or path
+    #[serde_inline_default("input-mouse".to_string())]
+    pub icon: String,
+}
+
+#[serde_inline_default]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverlayConfig {
+    /// How long (seconds) recognition result is shown at the end of the stroke, 0 disables it
+    #[serde_inline_default(0.8)]
+    pub toast_duration: f64,
+    /// Show best candidate while gesture is drawn, may be slow with many patterns
+    #[serde_inline_default(true)]
+    pub live_preview: bool,
+    /// Minimal time (seconds) between recognitions of unfinished gesture
+    #[serde_inline_default(0.1)]
+    pub preview_interval: f64,
+    /// Whether feedback is drawn at all
+    #[serde_inline_default(OverlayMode::Visible)]
+    pub mode: OverlayMode,
+    /// How overlay takes keyboard focus, Escape doesn't work without it
+    #[serde_inline_default(KeyboardMode::Exclusive)]
+    pub keyboard: KeyboardMode,
+    /// Cursor shape over the overlay, compositor default if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<CursorShape>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayMode {
+    /// Toast, live preview and sample counter are drawn
+    Visible,
+    /// Overlay stays fully transparent, only cursor shows that gesture is captured
+    Invisible,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyboardMode {
+    /// Overlay grabs keyboard until gesture is finished
+    Exclusive,
+    /// Overlay gets keyboard focus only when compositor gives it (e.g. on click)
+    OnDemand,
+    /// Keyboard stays with focused window
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorShape {
+    Default,
+    Crosshair,
+    Cell,
+    Pointer,
+    Move,
+    Grab,
+    Grabbing,
+    Hidden,
+}
+
+#[serde_inline_default]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureConfig {
+    /// How gesture is started
+    #[serde_inline_default(StartTrigger::Held)]
+    pub start_on: StartTrigger,
+    /// Events that finish gesture
+    #[serde_inline_default(vec![EndTrigger::Release])]
+    pub end_on: Vec<EndTrigger>,
+    /// Button used by `press`, `release` and `second_click` triggers, any button if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub button: Option<PointerButton>,
+    /// Finish gesture if pointer doesn't move for specified number of seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<f64>,
+    /// Finish gesture after specified number of seconds since capture start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration: Option<f64>,
+    /// Events that cancel gesture, in addition to Escape
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cancel_on: Vec<CancelTrigger>,
+    /// Distance (pixels) from the start point for `return_to_start` trigger
+    #[serde_inline_default(24.0)]
+    pub return_radius: f64,
+    /// Cancel gesture if its path is longer than specified number of pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_path_length: Option<f64>,
+    /// Lock pointer while gesture is drawn and build path from unaccelerated relative
+    /// motion, so stroke isn't clipped by screen edges
+    #[serde_inline_default(false)]
+    pub relative_pointer: bool,
+    /// Where pointer events come from
+    #[serde_inline_default(CaptureBackend::Wayland)]
+    pub backend: CaptureBackend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureBackend {
+    /// Overlay on top of outputs receives pointer events from compositor
+    Wayland,
+    /// Relative motion is read from /dev/input/event* of mice, without overlay
+    Evdev,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartTrigger {
+    /// Button that launched wlrune is still held, stroke starts where overlay appears
+    Held,
+    /// Wait for button press on the overlay, stroke starts at the press position
+    Press,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndTrigger {
+    /// Button release
+    Release,
+    /// Release of all keyboard modifiers (for gestures bound to keyboard shortcuts)
+    ModifierRelease,
+    /// Button press after capture start
+    SecondClick,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelTrigger {
+    /// Press of another button while gesture is drawn
+    SecondButton,
+    /// Scroll while gesture is drawn
+    Scroll,
+    /// Pointer moved away and came back to the start point (scribble-out)
+    ReturnToStart,
+}
+
+/// Mouse button by name or by evdev code (see linux/input-event-codes.h)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PointerButton {
+    Named(ButtonName),
+    Code(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ButtonName {
+    Left,
+    Right,
+    Middle,
+    Side,
+    Extra,
+    Forward,
+    Back,
+}
+
+impl PointerButton {
+    /// Button code used by wl_pointer and evdev
+    pub fn code(self) -> u32 {
+        match self {
+            PointerButton::Named(ButtonName::Left) => 0x110,
+            PointerButton::Named(ButtonName::Right) => 0x111,
+            PointerButton::Named(ButtonName::Middle) => 0x112,
+            PointerButton::Named(ButtonName::Side) => 0x113,
+            PointerButton::Named(ButtonName::Extra) => 0x114,
+            PointerButton::Named(ButtonName::Forward) => 0x115,
+            PointerButton::Named(ButtonName::Back) => 0x116,
+            PointerButton::Code(code) => code,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GestureCommand {
     pub pattern: String,
-    pub command: String,
+    pub command: CommandLine,
+    /// Working directory of command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Environment variables added to command environment
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Where to write command output: file path or `journal`. By default output discarded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<CommandOutput>,
+    /// Kill command if it runs longer than specified number of seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<f64>,
+    /// Wait for command and report non-zero exit status
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub report_failure: bool,
+    /// Use binding only for gestures started on output with this name (e.g. `DP-1`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_output: Option<String>,
+    /// Use binding only for gestures started in this part of the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_region: Option<StartRegion>,
+}
+
+/// Part of the output in logical pixels, relative to its top left corner
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StartRegion {
+    /// Strip along the edge of the output
+    Edge {
+        edge: ScreenEdge,
+        /// Width of the strip
+        #[serde(default = "default_edge_distance")]
+        distance: f64,
+    },
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenEdge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+fn default_edge_distance() -> f64 {
+    50.0
+}
+
+impl StartRegion {
+    /// Whether point relative to output of `output_size` is inside of region
+    pub fn contains(&self, x: f64, y: f64, output_size: (u32, u32)) -> bool {
+        let (width, height) = (output_size.0 as f64, output_size.1 as f64);
+        match *self {
+            StartRegion::Edge { edge, distance } => match edge {
+                ScreenEdge::Left => x < distance,
+                ScreenEdge::Right => x >= width - distance,
+                ScreenEdge::Top => y < distance,
+                ScreenEdge::Bottom => y >= height - distance,
+            },
+            StartRegion::Rect {
+                x: left,
+                y: top,
+                width,
+                height,
+            } => x >= left && x < left + width && y >= top && y < top + height,
+        }
+    }
+
+    /// Size of the region is positive
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            StartRegion::Edge { distance, .. } => distance > 0.0,
+            StartRegion::Rect { width, height, .. } => width > 0.0 && height > 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum CommandOutput {
+    /// systemd journal
+    Journal,
+    File(PathBuf),
+}
+
+impl From<String> for CommandOutput {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "journal" => CommandOutput::Journal,
+            _ => CommandOutput::File(PathBuf::from(value)),
+        }
+    }
+}
+
+impl From<CommandOutput> for String {
+    fn from(value: CommandOutput) -> Self {
+        match value {
+            CommandOutput::Journal => "journal".to_string(),
+            CommandOutput::File(path) => path.display().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CommandLine {
+    /// Command line executed by shell
+    Shell(String),
+    /// Program with arguments executed directly
+    Argv(Vec<String>),
+}
+
+impl CommandLine {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            CommandLine::Shell(command) => command.trim().is_empty(),
+            CommandLine::Argv(argv) => argv.first().is_none_or(|program| program.is_empty()),
+        }
+    }
+}
+
+impl GestureCommand {
+    /// Whether wlrune should wait for command instead of detaching it
+    pub fn is_supervised(&self) -> bool {
+        self.output.is_some() || self.timeout.is_some() || self.report_failure
+    }
+
+    pub fn new(pattern: String, command: String) -> Self {
+        GestureCommand {
+            pattern,
+            command: CommandLine::Shell(command),
+            cwd: None,
+            env: BTreeMap::new(),
+            output: None,
+            timeout: None,
+            report_failure: false,
+            on_output: None,
+            start_region: None,
+        }
+    }
+
+    /// Binding is used only for gestures started on particular output or region
+    pub fn is_restricted(&self) -> bool {
+        self.on_output.is_some() || self.start_region.is_some()
+    }
+
+    /// Earlier binding of the same pattern takes all gestures that later one would take
+    pub fn shadows(&self, later: &GestureCommand) -> bool {
+        self.pattern == later.pattern
+            && self
+                .on_output
+                .as_ref()
+                .is_none_or(|output| later.on_output.as_ref() == Some(output))
+            && self
+                .start_region
+                .is_none_or(|region| later.start_region == Some(region))
+    }
 }
 
 impl AppConfig {
@@ -61,24 +427,206 @@ impl AppConfig {
             eprintln!("ERROR: failed to parse config {}, {}", config_path.display(), err);
         })?;
 
-        let exec_treshold = config.recognizer.command_execute_treshold;
-        if exec_treshold < 0.0 || exec_treshold > 1.0 {
-            eprintln!("ERROR: recognizer.command_execute_treshold should be in range [0,1]");
+        let errors = config.validate();
+        for error in &errors {
+            eprintln!("ERROR: {}", error.message);
+        }
+        if !errors.is_empty() {
             return Err(());
         }
 
-        if config.recognizer.width <= 0.0 {
-            eprintln!("ERROR: recognizer.width should be positive number");
-            return Err(());
+        Ok(config)
+    }
+
+    /// Values that can be parsed but can't be used, shared by loading and `config check`
+    pub fn validate(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        let mut error = |path: &[Segment<'static>], message: String| {
+            errors.push(ConfigError {
+                path: path.to_vec(),
+                message,
+            });
+        };
+        let requirement = |path: &[Segment], requirement: &str| {
+            format!("`{}` {}", display_path(path), requirement)
+        };
+
+        let recognizer = &self.recognizer;
+        let recognizer_fields = [
+            (
+                "command_execute_treshold",
+                (0.0..=1.0).contains(&recognizer.command_execute_treshold),
+                "should be in range [0,1]",
+            ),
+            (
+                "rotation_angle_range",
+                recognizer.rotation_angle_range >= 0.0,
+                "should not be negative",
+            ),
+            (
+                "rotation_angle_treshold",
+                recognizer.rotation_angle_treshold > 0.0,
+                "should be positive number",
+            ),
+            (
+                "resample_num_points",
+                recognizer.resample_num_points >= 2,
+                "should be at least 2",
+            ),
+            ("width", recognizer.width > 0.0, "should be positive number"),
+            (
+                "height",
+                recognizer.height > 0.0,
+                "should be positive number",
+            ),
+        ];
+        for (field, is_valid, description) in recognizer_fields {
+            if !is_valid {
+                let path = [Segment::Key("recognizer"), Segment::Key(field)];
+                error(&path, requirement(&path, description));
+            }
         }
 
-        if config.recognizer.height <= 0.0 {
-            eprintln!("ERROR: recognizer.height should be positive number");
-            return Err(());
+        let durations = [
+            ("toast_duration", self.overlay.toast_duration),
+            ("preview_interval", self.overlay.preview_interval),
+        ];
+        for (field, value) in durations {
+            if value < 0.0 {
+                let path = [Segment::Key("overlay"), Segment::Key(field)];
+                error(&path, requirement(&path, "should not be negative"));
+            }
         }
 
-        Ok(config)
+        // modifiers are delivered only to surface with keyboard focus
+        if self.overlay.keyboard != KeyboardMode::Exclusive
+            && self.capture.end_on.contains(&EndTrigger::ModifierRelease)
+        {
+            let path = [Segment::Key("overlay"), Segment::Key("keyboard")];
+            error(
+                &path,
+                requirement(
+                    &path,
+                    "should be exclusive for `modifier_release` end trigger",
+                ),
+            );
+        }
+
+        let capture = &self.capture;
+        let positive_fields = [
+            ("idle_timeout", capture.idle_timeout),
+            ("max_duration", capture.max_duration),
+            ("return_radius", Some(capture.return_radius)),
+            ("max_path_length", capture.max_path_length),
+        ];
+        for (field, value) in positive_fields {
+            if value.is_some_and(|value| value <= 0.0) {
+                let path = [Segment::Key("capture"), Segment::Key(field)];
+                error(&path, requirement(&path, "should be positive number"));
+            }
+        }
+
+        let end_on = [Segment::Key("capture"), Segment::Key("end_on")];
+        if capture.backend == CaptureBackend::Evdev
+            && capture.end_on.contains(&EndTrigger::ModifierRelease)
+        {
+            error(
+                &end_on,
+                format!(
+                    "`modifier_release` in `{}` is not supported by evdev backend",
+                    display_path(&end_on)
+                ),
+            );
+        }
+        if !capture.can_finish() {
+            error(
+                &end_on,
+                requirement(
+                    &end_on,
+                    "is empty and no timeout set, gesture can't be finished",
+                ),
+            );
+        }
+
+        for (index, cmd) in self.commands.iter().enumerate() {
+            let field = |name| {
+                [
+                    Segment::Key("commands"),
+                    Segment::Index(index),
+                    Segment::Key(name),
+                ]
+            };
+
+            if cmd.command.is_empty() {
+                error(
+                    &field("command"),
+                    format!("command for pattern `{}` is empty", cmd.pattern),
+                );
+            }
+
+            if cmd
+                .timeout
+                .is_some_and(|timeout| !(timeout > 0.0 && timeout.is_finite()))
+            {
+                let path = field("timeout");
+                error(
+                    &path,
+                    requirement(&path, "should be positive number of seconds"),
+                );
+            }
+
+            if cmd.start_region.is_some_and(|region| !region.is_valid()) {
+                let path = field("start_region");
+                error(&path, requirement(&path, "should have positive size"));
+            }
+        }
+
+        errors
+    }
+}
+
+/// Step of path to config value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// Path in `commands[0].pattern` form
+pub fn display_path(path: &[Segment]) -> String {
+    let mut result = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if result.is_empty() => result.push_str(key),
+            Segment::Key(key) => {
+                result.push('.');
+                result.push_str(key);
+            }
+            Segment::Index(index) => result.push_str(&format!("[{index}]")),
+        }
     }
+    result
+}
+
+/// Value of config that doesn't meet requirements
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: Vec<Segment<'static>>,
+    pub message: String,
+}
+
+/// Print commands in config format, so user can paste them to `commands` section
+pub fn print_commands(commands: &[GestureCommand]) -> Result<(), ()> {
+    let raw = serde_yml::to_string(commands).map_err(|err| {
+        eprintln!("ERROR: failed to serialize commands: {err}");
+    })?;
+
+    eprintln!("add following entries to `commands` section of your config:");
+    io::stdout().write_all(raw.as_bytes()).map_err(|err| {
+        eprintln!("ERROR: couldn't write commands to stdout: {err}");
+    })?;
+
+    Ok(())
 }
 
 impl Default for AppConfig {
@@ -87,9 +635,33 @@ impl Default for AppConfig {
     }
 }
 
-impl Default for RecognizerConfig {
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        serde_yml::from_str("").unwrap()
+    }
+}
+
+impl CaptureConfig {
+    /// Whether any trigger other than Escape finishes gesture
+    pub fn can_finish(&self) -> bool {
+        !self.end_on.is_empty() || self.idle_timeout.is_some() || self.max_duration.is_some()
+    }
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        serde_yml::from_str("").unwrap()
+    }
+}
+
+impl Default for OverlayConfig {
     fn default() -> Self {
         serde_yml::from_str("").unwrap()
     }
 }
 
+impl Default for RecognizerConfig {
+    fn default() -> Self {
+        serde_yml::from_str("").unwrap()
+    }
+}
@@ -0,0 +1,606 @@
+use std::{fs::read_to_string, path::Path};
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_yml::Value;
+
+use crate::{
+    config::{
+        display_path, AppConfig, CaptureConfig, GestureCommand, NotificationConfig, OverlayConfig,
+        RecognizerConfig, Segment,
+    },
+    patterns::{gesture_file_path, PointerGesture},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Error,
+    Warning,
+}
+
+struct Diagnostic {
+    level: Level,
+    /// Line and column (1-based) of related key, if it can be located
+    position: Option<(usize, usize)>,
+    message: String,
+}
+
+/// Validate config file and print diagnostics in `file:line:column: level: message` form
+pub fn check_config(config_path: &Path) -> Result<(), ()> {
+    let raw = read_to_string(config_path).map_err(|err| {
+        eprintln!(
+            "ERROR: failed to read from config {}, {}",
+            config_path.display(),
+            err
+        );
+    })?;
+
+    let mut diagnostics = diagnose(&raw);
+    diagnostics.sort_by_key(|d| d.position.unwrap_or((usize::MAX, usize::MAX)));
+
+    for diagnostic in &diagnostics {
+        let level = match diagnostic.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+        match diagnostic.position {
+            Some((line, column)) => eprintln!(
+                "{}:{}:{}: {}: {}",
+                config_path.display(),
+                line,
+                column,
+                level,
+                diagnostic.message
+            ),
+            None => eprintln!(
+                "{}: {}: {}",
+                config_path.display(),
+                level,
+                diagnostic.message
+            ),
+        }
+    }
+
+    let error_count = diagnostics
+        .iter()
+        .filter(|d| d.level == Level::Error)
+        .count();
+    let warning_count = diagnostics.len() - error_count;
+
+    if error_count > 0 {
+        eprintln!("{} error(s), {} warning(s)", error_count, warning_count);
+        return Err(());
+    }
+
+    eprintln!("config is valid ({} warning(s))", warning_count);
+
+    Ok(())
+}
+
+fn diagnose(raw: &str) -> Vec<Diagnostic> {
+    let locator = YamlLocator::new(raw);
+    let mut diagnostics = Vec::new();
+
+    let value: Value = match serde_yml::from_str(raw) {
+        Ok(value) => value,
+        Err(err) => {
+            diagnostics.push(Diagnostic {
+                level: Level::Error,
+                position: err.location().map(|l| (l.line(), l.column())),
+                message: format!("invalid yaml: {}", err),
+            });
+            return diagnostics;
+        }
+    };
+
+    check_unknown_fields(&value, &locator, &mut diagnostics);
+
+    let config: AppConfig = match serde_yml::from_value(value) {
+        Ok(config) => config,
+        Err(err) => {
+            // value is already parsed, so location is lost, try to rerun on raw config
+            let position = serde_yml::from_str::<AppConfig>(raw)
+                .err()
+                .and_then(|err| err.location())
+                .map(|l| (l.line(), l.column()));
+            diagnostics.push(Diagnostic {
+                level: Level::Error,
+                position,
+                message: err.to_string(),
+            });
+            return diagnostics;
+        }
+    };
+
+    for error in config.validate() {
+        diagnostics.push(Diagnostic {
+            level: Level::Error,
+            position: locator.locate(&error.path),
+            message: error.message,
+        });
+    }
+    check_commands(&config.commands, &locator, &mut diagnostics);
+
+    diagnostics
+}
+
+fn check_unknown_fields(value: &Value, locator: &YamlLocator, diagnostics: &mut Vec<Diagnostic>) {
+    let Value::Mapping(root) = value else {
+        // empty config is valid, anything else reported by deserialization
+        return;
+    };
+
+    let mut unknown = |path: &[Segment], key: &Value, known: &[&str]| {
+        let Some(key) = key.as_str() else {
+            return;
+        };
+        if known.contains(&key) {
+            return;
+        }
+
+        let mut key_path = path.to_vec();
+        key_path.push(Segment::Key(key));
+        diagnostics.push(Diagnostic {
+            level: Level::Error,
+            position: locator.locate(&key_path),
+            message: format!(
+                "unknown field `{}`, expected one of {}",
+                display_path(&key_path),
+                known
+                    .iter()
+                    .map(|f| format!("`{f}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        });
+    };
+
+    for (key, value) in root {
+        unknown(&[], key, struct_fields::<AppConfig>());
+
+        match (key.as_str(), value) {
+            (Some("recognizer"), Value::Mapping(recognizer)) => {
+                for key in recognizer.keys() {
+                    unknown(
+                        &[Segment::Key("recognizer")],
+                        key,
+                        struct_fields::<RecognizerConfig>(),
+                    );
+                }
+            }
+            (Some("notifications"), Value::Mapping(notifications)) => {
+                for key in notifications.keys() {
+                    unknown(
+                        &[Segment::Key("notifications")],
+                        key,
+                        struct_fields::<NotificationConfig>(),
+                    );
+                }
+            }
+            (Some("overlay"), Value::Mapping(overlay)) => {
+                for key in overlay.keys() {
+                    unknown(
+                        &[Segment::Key("overlay")],
+                        key,
+                        struct_fields::<OverlayConfig>(),
+                    );
+                }
+            }
+            (Some("capture"), Value::Mapping(capture)) => {
+                for key in capture.keys() {
+                    unknown(
+                        &[Segment::Key("capture")],
+                        key,
+                        struct_fields::<CaptureConfig>(),
+                    );
+                }
+            }
+            (Some("commands"), Value::Sequence(commands)) => {
+                for (index, command) in commands.iter().enumerate() {
+                    let Value::Mapping(command) = command else {
+                        continue;
+                    };
+                    for key in command.keys() {
+                        unknown(
+                            &[Segment::Key("commands"), Segment::Index(index)],
+                            key,
+                            struct_fields::<GestureCommand>(),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn check_commands(
+    commands: &[GestureCommand],
+    locator: &YamlLocator,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (index, cmd) in commands.iter().enumerate() {
+        let pattern_path = [
+            Segment::Key("commands"),
+            Segment::Index(index),
+            Segment::Key("pattern"),
+        ];
+
+        // bindings restricted to different outputs or regions don't shadow each other
+        if let Some(first_index) = commands[..index]
+            .iter()
+            .position(|earlier| earlier.shadows(cmd))
+        {
+            let first_position = locator
+                .locate(&[
+                    Segment::Key("commands"),
+                    Segment::Index(first_index),
+                    Segment::Key("pattern"),
+                ])
+                .map(|(line, _)| format!(" (line {line})"))
+                .unwrap_or_default();
+            diagnostics.push(Diagnostic {
+                level: Level::Warning,
+                position: locator.locate(&pattern_path),
+                message: format!(
+                    "pattern `{}` already bound by commands[{}]{}, this binding is never used",
+                    cmd.pattern, first_index, first_position
+                ),
+            });
+        }
+
+        let is_recorded = PointerGesture::from_name(&cmd.pattern).is_some()
+            || gesture_file_path(&cmd.pattern).is_ok_and(|path| path.is_file());
+        if !is_recorded {
+            diagnostics.push(Diagnostic {
+                level: Level::Error,
+                position: locator.locate(&pattern_path),
+                message: format!(
+                    "pattern `{}` is not recorded, use `wlrune record --name {}`",
+                    cmd.pattern, cmd.pattern
+                ),
+            });
+        }
+
+        if let Some(cwd) = &cmd.cwd {
+            if !cwd.is_dir() {
+                diagnostics.push(Diagnostic {
+                    level: Level::Warning,
+                    position: locator.locate(&[
+                        Segment::Key("commands"),
+                        Segment::Index(index),
+                        Segment::Key("cwd"),
+                    ]),
+                    message: format!("cwd `{}` is not a directory", cwd.display()),
+                });
+            }
+        }
+    }
+}
+
+/// Finds positions of keys in block style yaml, serde_yml doesn't keep them after parsing
+struct YamlLocator<'a> {
+    lines: Vec<&'a str>,
+}
+
+/// Lines of yaml node, content of the first line starts from `first_column`
+struct Node {
+    start: usize,
+    end: usize,
+    first_column: usize,
+}
+
+impl<'a> YamlLocator<'a> {
+    fn new(raw: &'a str) -> Self {
+        YamlLocator {
+            lines: raw.lines().collect(),
+        }
+    }
+
+    /// Line and column (1-based) of the last segment in path
+    fn locate(&self, path: &[Segment]) -> Option<(usize, usize)> {
+        let mut node = Node {
+            start: 0,
+            end: self.lines.len(),
+            first_column: 0,
+        };
+        let mut position = None;
+
+        for segment in path {
+            let entries = (node.start..node.end)
+                .filter_map(|line| Some((line, self.content_column(&node, line)?)))
+                .collect::<Vec<_>>();
+            // all entries of block share indentation of the first one
+            let &(_, block_column) = entries.first()?;
+            let is_item = |&(line, column): &(usize, usize)| {
+                column == block_column && self.lines[line][column..].starts_with('-')
+            };
+
+            match segment {
+                Segment::Key(key) => {
+                    let (index, &(line, column)) =
+                        entries.iter().enumerate().find(|(_, (line, column))| {
+                            *column == block_column && is_key(&self.lines[*line][*column..], key)
+                        })?;
+
+                    // list may be indented on the same level as its key
+                    let end = entries[index + 1..]
+                        .iter()
+                        .find(|entry| entry.1 <= block_column && !is_item(entry))
+                        .map_or(node.end, |entry| entry.0);
+                    let colon = column + self.lines[line][column..].find(':')?;
+
+                    position = Some((line + 1, column + 1));
+                    node = Node {
+                        start: line,
+                        end,
+                        first_column: colon + 1,
+                    };
+                }
+                Segment::Index(index) => {
+                    let items = entries
+                        .iter()
+                        .filter(|entry| is_item(entry))
+                        .collect::<Vec<_>>();
+                    let &&(line, column) = items.get(*index)?;
+                    let end = items.get(index + 1).map_or(node.end, |entry| entry.0);
+
+                    position = Some((line + 1, column + 1));
+                    node = Node {
+                        start: line,
+                        end,
+                        first_column: column + 1,
+                    };
+                }
+            }
+        }
+
+        position
+    }
+
+    /// Column where content of line starts, `None` for blank and comment lines
+    fn content_column(&self, node: &Node, line_index: usize) -> Option<usize> {
+        let line = self.lines[line_index];
+        let from = if line_index == node.start {
+            node.first_column
+        } else {
+            0
+        };
+
+        let rest = line.get(from..)?;
+        let column = from + rest.len() - rest.trim_start().len();
+        let content = line[column..].trim_end();
+        if content.is_empty() || content.starts_with('#') {
+            return None;
+        }
+
+        Some(column)
+    }
+}
+
+fn is_key(content: &str, key: &str) -> bool {
+    let rest = content
+        .strip_prefix(key)
+        .or_else(|| {
+            content
+                .strip_prefix('"')
+                .and_then(|r| r.strip_prefix(key))
+                .and_then(|r| r.strip_prefix('"'))
+        })
+        .or_else(|| {
+            content
+                .strip_prefix('\'')
+                .and_then(|r| r.strip_prefix(key))
+                .and_then(|r| r.strip_prefix('\''))
+        });
+
+    rest.is_some_and(|r| r.trim_start().starts_with(':'))
+}
+
+/// Field names of struct as declared for serde
+fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct FieldsDeserializer<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for FieldsDeserializer<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("only structs supported"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("fields collected"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldsDeserializer(&mut fields));
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+# wlrune config
+recognizer:
+  # similarity
+  command_execute_treshold: 0.8
+
+  width: 100.0
+capture:
+  end_on: [release, second_click]
+  button: right
+commands:
+  - pattern: up
+    command: firefox
+  - pattern: \"down\"
+    # list of arguments
+    command: [\"kitty\", \"-1\"]
+    env:
+      LANG: C
+  -   pattern: left
+      command: \"true\"
+";
+
+    fn locate(path: &[Segment]) -> Option<(usize, usize)> {
+        YamlLocator::new(CONFIG).locate(path)
+    }
+
+    #[test]
+    fn nested_keys_are_located() {
+        assert_eq!(locate(&[Segment::Key("recognizer")]), Some((2, 1)));
+        assert_eq!(
+            locate(&[
+                Segment::Key("recognizer"),
+                Segment::Key("command_execute_treshold")
+            ]),
+            Some((4, 3))
+        );
+        // blank line and comment don't end the block
+        assert_eq!(
+            locate(&[Segment::Key("recognizer"), Segment::Key("width")]),
+            Some((6, 3))
+        );
+        assert_eq!(
+            locate(&[Segment::Key("recognizer"), Segment::Key("button")]),
+            None
+        );
+    }
+
+    #[test]
+    fn sequence_items_are_located() {
+        let pattern = |index| {
+            locate(&[
+                Segment::Key("commands"),
+                Segment::Index(index),
+                Segment::Key("pattern"),
+            ])
+        };
+
+        assert_eq!(pattern(0), Some((11, 5)));
+        // quoted key and item with extra indentation
+        assert_eq!(pattern(1), Some((13, 5)));
+        assert_eq!(pattern(2), Some((18, 7)));
+        assert_eq!(pattern(3), None);
+
+        assert_eq!(
+            locate(&[
+                Segment::Key("commands"),
+                Segment::Index(1),
+                Segment::Key("env"),
+                Segment::Key("LANG")
+            ]),
+            Some((17, 7))
+        );
+        // key of another item isn't found in this one
+        assert_eq!(
+            locate(&[
+                Segment::Key("commands"),
+                Segment::Index(0),
+                Segment::Key("env")
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn flow_sequence_doesnt_hide_following_keys() {
+        assert_eq!(
+            locate(&[Segment::Key("capture"), Segment::Key("end_on")]),
+            Some((8, 3))
+        );
+        assert_eq!(
+            locate(&[Segment::Key("capture"), Segment::Key("button")]),
+            Some((9, 3))
+        );
+        assert_eq!(
+            locate(&[
+                Segment::Key("commands"),
+                Segment::Index(1),
+                Segment::Key("command")
+            ]),
+            Some((15, 5))
+        );
+        // items of flow sequence aren't located
+        assert_eq!(
+            locate(&[
+                Segment::Key("capture"),
+                Segment::Key("end_on"),
+                Segment::Index(0)
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn commented_out_keys_are_ignored() {
+        let raw = "capture:\n  # button: left\n  button: right\n";
+        assert_eq!(
+            YamlLocator::new(raw).locate(&[Segment::Key("capture"), Segment::Key("button")]),
+            Some((3, 3))
+        );
+    }
+
+    #[test]
+    fn shadowed_binding_is_still_checked() {
+        let raw = "commands:\n  - pattern: wheel_up\n    command: \"true\"\n  - pattern: wheel_up\n    command: []\n";
+        let messages = diagnose(raw)
+            .into_iter()
+            .map(|d| (d.position, d.message))
+            .collect::<Vec<_>>();
+
+        assert!(
+            messages
+                .iter()
+                .any(|(position, message)| *position == Some((4, 5))
+                    && message.contains("never used")),
+            "{messages:?}"
+        );
+        assert!(
+            messages.iter().any(
+                |(position, message)| *position == Some((5, 5)) && message.contains("is empty")
+            ),
+            "{messages:?}"
+        );
+    }
+
+    #[test]
+    fn validation_errors_are_positioned() {
+        let raw = "recognizer:\n  width: -1\ncapture:\n  end_on: []\n";
+        let messages = diagnose(raw)
+            .into_iter()
+            .map(|d| (d.position, d.message))
+            .collect::<Vec<_>>();
+
+        assert!(
+            messages.contains(&(
+                Some((2, 3)),
+                "`recognizer.width` should be positive number".to_string()
+            )),
+            "{messages:?}"
+        );
+        assert!(
+            messages
+                .iter()
+                .any(|(position, message)| *position == Some((4, 3))
+                    && message.contains("can't be finished")),
+            "{messages:?}"
+        );
+    }
+}
@@ -0,0 +1,754 @@
+use std::{
+    env,
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    time::Duration,
+};
+
+const MESSAGE_METHOD_CALL: u8 = 1;
+const MESSAGE_METHOD_RETURN: u8 = 2;
+const MESSAGE_ERROR: u8 = 3;
+
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SIGNATURE: u8 = 8;
+
+/// Minimal D-Bus client, just enough to call methods of session services
+pub struct BusConnection {
+    stream: UnixStream,
+    serial: u32,
+}
+
+impl BusConnection {
+    /// Connect to bus from $DBUS_SESSION_BUS_ADDRESS
+    pub fn session() -> io::Result<Self> {
+        let address = env::var("DBUS_SESSION_BUS_ADDRESS")
+            .map_err(|_| io::Error::other("DBUS_SESSION_BUS_ADDRESS is not set"))?;
+        Self::connect(&address)
+    }
+
+    /// Connect to bus by address (`unix:path=...` or `unix:abstract=...`)
+    pub fn connect(address: &str) -> io::Result<Self> {
+        Self::from_stream(connect_unix(address)?)
+    }
+
+    /// Authenticate and register on bus over already connected socket
+    pub fn from_stream(stream: UnixStream) -> io::Result<Self> {
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        stream.set_write_timeout(Some(Duration::from_secs(2)))?;
+
+        let mut connection = BusConnection { stream, serial: 0 };
+        connection.authenticate()?;
+        connection.call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "Hello",
+            "",
+            &[],
+        )?;
+
+        Ok(connection)
+    }
+
+    /// Call method and wait for reply, returns reply body
+    pub fn call(
+        &mut self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        signature: &str,
+        body: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        self.serial += 1;
+        let serial = self.serial;
+
+        let mut message = MessageWriter::new();
+        message.u8(b'l');
+        message.u8(MESSAGE_METHOD_CALL);
+        message.u8(0);
+        message.u8(1);
+        message.u32(body.len() as u32);
+        message.u32(serial);
+
+        let mut fields = vec![
+            (FIELD_PATH, "o", path),
+            (FIELD_DESTINATION, "s", destination),
+            (FIELD_INTERFACE, "s", interface),
+            (FIELD_MEMBER, "s", member),
+        ];
+        if !signature.is_empty() {
+            fields.push((FIELD_SIGNATURE, "g", signature));
+        }
+
+        message.array(8, |message| {
+            for (code, kind, value) in &fields {
+                message.align(8);
+                message.u8(*code);
+                message.signature(kind);
+                match *kind {
+                    "g" => message.signature(value),
+                    _ => message.string(value),
+                }
+            }
+        });
+        message.align(8);
+        message.bytes(body);
+
+        self.stream.write_all(&message.buf)?;
+
+        loop {
+            let reply = self.read_message()?;
+            if reply.reply_serial != Some(serial) {
+                continue;
+            }
+
+            return match reply.kind {
+                MESSAGE_METHOD_RETURN => Ok(reply.body),
+                MESSAGE_ERROR => Err(io::Error::other(format!(
+                    "{}: {}",
+                    reply.error_name.unwrap_or_default(),
+                    reply.error_message.unwrap_or_default()
+                ))),
+                _ => continue,
+            };
+        }
+    }
+
+    fn authenticate(&mut self) -> io::Result<()> {
+        let hex_uid = rustix::process::getuid()
+            .as_raw()
+            .to_string()
+            .bytes()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+
+        self.stream.write_all(b"\0")?;
+        self.stream
+            .write_all(format!("AUTH EXTERNAL {hex_uid}\r\n").as_bytes())?;
+
+        let response = self.read_line()?;
+        if !response.starts_with("OK ") {
+            return Err(io::Error::other(format!(
+                "authentication rejected: {}",
+                response.trim_end()
+            )));
+        }
+
+        self.stream.write_all(b"BEGIN\r\n")
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while !line.ends_with(b"\r\n") {
+            self.stream.read_exact(&mut byte)?;
+            line.push(byte[0]);
+        }
+
+        Ok(String::from_utf8_lossy(&line).to_string())
+    }
+
+    fn read_message(&mut self) -> io::Result<Message> {
+        let mut fixed = [0u8; 16];
+        self.stream.read_exact(&mut fixed)?;
+
+        let reader = MessageReader {
+            buf: &fixed,
+            pos: 0,
+            big_endian: fixed[0] == b'B',
+        };
+        let kind = fixed[1];
+        let body_len = reader.u32_at(4) as usize;
+        let fields_len = reader.u32_at(12) as usize;
+        let header_len = (16 + fields_len).next_multiple_of(8);
+
+        let mut rest = vec![0u8; header_len - 16 + body_len];
+        self.stream.read_exact(&mut rest)?;
+
+        let mut buf = fixed.to_vec();
+        buf.extend_from_slice(&rest);
+
+        let mut message = Message {
+            kind,
+            reply_serial: None,
+            error_name: None,
+            error_message: None,
+            body: buf[header_len..].to_vec(),
+        };
+
+        let mut reader = MessageReader {
+            buf: &buf[..16 + fields_len],
+            pos: 16,
+            big_endian: fixed[0] == b'B',
+        };
+        let mut body_signature = String::new();
+        while reader.pos < reader.buf.len() {
+            reader.align(8);
+            let code = reader.u8()?;
+            let kind = reader.signature()?;
+            match kind.as_str() {
+                "u" => {
+                    let value = reader.u32()?;
+                    if code == FIELD_REPLY_SERIAL {
+                        message.reply_serial = Some(value);
+                    }
+                }
+                "s" | "o" => {
+                    let value = reader.string()?;
+                    if code == FIELD_ERROR_NAME {
+                        message.error_name = Some(value);
+                    }
+                }
+                "g" => {
+                    let value = reader.signature()?;
+                    if code == FIELD_SIGNATURE {
+                        body_signature = value;
+                    }
+                }
+                _ => {
+                    return Err(io::Error::other(format!(
+                        "unsupported header field type {kind}"
+                    )))
+                }
+            }
+        }
+
+        if kind == MESSAGE_ERROR && body_signature.starts_with('s') {
+            let mut reader = MessageReader {
+                buf: &message.body,
+                pos: 0,
+                big_endian: fixed[0] == b'B',
+            };
+            message.error_message = reader.string().ok();
+        }
+
+        Ok(message)
+    }
+}
+
+struct Message {
+    kind: u8,
+    reply_serial: Option<u32>,
+    error_name: Option<String>,
+    error_message: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Little endian marshalling of D-Bus values
+#[derive(Default)]
+pub struct MessageWriter {
+    pub buf: Vec<u8>,
+}
+
+impl MessageWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn align(&mut self, alignment: usize) {
+        self.buf
+            .resize(self.buf.len().next_multiple_of(alignment), 0);
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn i32(&mut self, value: i32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) {
+        self.buf.extend_from_slice(value);
+    }
+
+    pub fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    pub fn signature(&mut self, value: &str) {
+        self.u8(value.len() as u8);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    /// Array with elements aligned to `element_alignment`, content written by `write`
+    pub fn array(&mut self, element_alignment: usize, write: impl FnOnce(&mut Self)) {
+        self.u32(0);
+        let len_pos = self.buf.len() - 4;
+        self.align(element_alignment);
+        let start = self.buf.len();
+
+        write(self);
+
+        let len = (self.buf.len() - start) as u32;
+        self.buf[len_pos..len_pos + 4].copy_from_slice(&len.to_le_bytes());
+    }
+}
+
+struct MessageReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl MessageReader<'_> {
+    fn align(&mut self, alignment: usize) {
+        self.pos = self.pos.next_multiple_of(alignment);
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&[u8]> {
+        let end = self.pos + len;
+        let data = self
+            .buf
+            .get(self.pos..end)
+            .ok_or_else(|| io::Error::other("truncated message"))?;
+        self.pos = end;
+        Ok(data)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32_at(&self, pos: usize) -> u32 {
+        let bytes: [u8; 4] = self.buf[pos..pos + 4].try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.align(4);
+        self.take(4)?;
+        Ok(self.u32_at(self.pos - 4))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u32()? as usize;
+        let value = String::from_utf8_lossy(self.take(len)?).to_string();
+        self.take(1)?;
+        Ok(value)
+    }
+
+    fn signature(&mut self) -> io::Result<String> {
+        let len = self.u8()? as usize;
+        let value = String::from_utf8_lossy(self.take(len)?).to_string();
+        self.take(1)?;
+        Ok(value)
+    }
+}
+
+fn connect_unix(address: &str) -> io::Result<UnixStream> {
+    for alternative in address.split(';') {
+        let Some(params) = alternative.strip_prefix("unix:") else {
+            continue;
+        };
+
+        for param in params.split(',') {
+            if let Some(path) = param.strip_prefix("path=") {
+                return UnixStream::connect(unescape(path));
+            }
+
+            if let Some(name) = param.strip_prefix("abstract=") {
+                use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+
+                let addr = SocketAddr::from_abstract_name(unescape(name).as_bytes())?;
+                return UnixStream::connect_addr(&addr);
+            }
+        }
+    }
+
+    Err(io::Error::other(format!(
+        "unsupported bus address {address}"
+    )))
+}
+
+/// Values in bus address may contain %-escaped bytes
+fn unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 3 <= bytes.len() {
+            let byte = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = byte {
+                result.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&result).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, thread};
+
+    use super::*;
+    use crate::{
+        config::NotificationConfig,
+        notify::{send_notification, Urgency},
+    };
+
+    /// Method call as received by the bus
+    struct Call {
+        serial: u32,
+        fields: HashMap<u8, String>,
+        body: Vec<u8>,
+    }
+
+    /// Bus daemon side of socket pair, speaks the way dbus-daemon does
+    struct StandIn {
+        stream: UnixStream,
+        serial: u32,
+    }
+
+    impl StandIn {
+        /// Accept or reject authentication, returns AUTH command sent by client
+        fn authenticate(&mut self, accept: bool) -> String {
+            let mut nul = [0u8];
+            self.stream.read_exact(&mut nul).unwrap();
+            assert_eq!(nul, [0]);
+
+            let auth = self.read_line();
+            if !accept {
+                self.stream.write_all(b"REJECTED EXTERNAL\r\n").unwrap();
+                return auth;
+            }
+
+            self.stream
+                .write_all(b"OK 1234deadbeef1234deadbeef1234de\r\n")
+                .unwrap();
+            assert_eq!(self.read_line(), "BEGIN");
+
+            auth
+        }
+
+        fn read_line(&mut self) -> String {
+            let mut line = Vec::new();
+            let mut byte = [0u8];
+            while !line.ends_with(b"\r\n") {
+                self.stream.read_exact(&mut byte).unwrap();
+                line.push(byte[0]);
+            }
+            line.truncate(line.len() - 2);
+            String::from_utf8(line).unwrap()
+        }
+
+        fn read_call(&mut self) -> Call {
+            let mut fixed = [0u8; 16];
+            self.stream.read_exact(&mut fixed).unwrap();
+            assert_eq!(&fixed[..4], &[b'l', MESSAGE_METHOD_CALL, 0, 1]);
+
+            let header = MessageReader {
+                buf: &fixed,
+                pos: 0,
+                big_endian: false,
+            };
+            let body_len = header.u32_at(4) as usize;
+            let serial = header.u32_at(8);
+            let fields_len = header.u32_at(12) as usize;
+            let header_len = (16 + fields_len).next_multiple_of(8);
+
+            let mut buf = fixed.to_vec();
+            buf.resize(header_len + body_len, 0);
+            self.stream.read_exact(&mut buf[16..]).unwrap();
+            // header is padded with zeroes up to the body
+            assert!(buf[16 + fields_len..header_len].iter().all(|b| *b == 0));
+
+            let mut reader = MessageReader {
+                buf: &buf[..16 + fields_len],
+                pos: 16,
+                big_endian: false,
+            };
+            let mut fields = HashMap::new();
+            while reader.pos < reader.buf.len() {
+                reader.align(8);
+                let code = reader.u8().unwrap();
+                let value = match reader.signature().unwrap().as_str() {
+                    "g" => reader.signature().unwrap(),
+                    "s" | "o" => reader.string().unwrap(),
+                    kind => panic!("unexpected field type {kind}"),
+                };
+                fields.insert(code, value);
+            }
+
+            Call {
+                serial,
+                fields,
+                body: buf[header_len..].to_vec(),
+            }
+        }
+
+        fn reply(&mut self, call: &Call, signature: &str, body: &[u8]) {
+            self.send(MESSAGE_METHOD_RETURN, call, None, signature, body);
+        }
+
+        fn reply_error(&mut self, call: &Call, name: &str, text: &str) {
+            let mut body = MessageWriter::new();
+            body.string(text);
+            self.send(MESSAGE_ERROR, call, Some(name), "s", &body.buf);
+        }
+
+        fn send(
+            &mut self,
+            kind: u8,
+            call: &Call,
+            error: Option<&str>,
+            signature: &str,
+            body: &[u8],
+        ) {
+            self.serial += 1;
+
+            let mut message = MessageWriter::new();
+            message.bytes(&[b'l', kind, 0, 1]);
+            message.u32(body.len() as u32);
+            message.u32(self.serial);
+            message.array(8, |message| {
+                message.align(8);
+                message.u8(FIELD_REPLY_SERIAL);
+                message.signature("u");
+                message.u32(call.serial);
+
+                if let Some(error) = error {
+                    message.align(8);
+                    message.u8(FIELD_ERROR_NAME);
+                    message.signature("s");
+                    message.string(error);
+                }
+
+                message.align(8);
+                message.u8(FIELD_SIGNATURE);
+                message.signature("g");
+                message.signature(signature);
+            });
+            message.align(8);
+            message.bytes(body);
+
+            self.stream.write_all(&message.buf).unwrap();
+        }
+
+        /// Handshake and reply to Hello, as every connection starts
+        fn accept(&mut self) -> Call {
+            self.authenticate(true);
+            let hello = self.read_call();
+            let mut name = MessageWriter::new();
+            name.string(":1.42");
+            self.reply(&hello, "s", &name.buf);
+            hello
+        }
+    }
+
+    /// Run stand-in script in its own thread, client side of the pair is returned
+    fn stand_in<T: Send + 'static>(
+        script: impl FnOnce(&mut StandIn) -> T + Send + 'static,
+    ) -> (UnixStream, thread::JoinHandle<T>) {
+        let (client, server) = UnixStream::pair().unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut stand_in = StandIn {
+                stream: server,
+                serial: 0,
+            };
+            script(&mut stand_in)
+        });
+
+        (client, handle)
+    }
+
+    #[test]
+    fn auth_external_sends_hex_encoded_uid() {
+        let (client, stand_in) = stand_in(|bus| {
+            let auth = bus.authenticate(true);
+            let hello = bus.read_call();
+            bus.reply(&hello, "s", &[5, 0, 0, 0, b':', b'1', b'.', b'4', b'2', 0]);
+            auth
+        });
+
+        BusConnection::from_stream(client).unwrap();
+
+        let uid = rustix::process::getuid().as_raw().to_string();
+        let hex_uid = uid.bytes().map(|b| format!("{b:02x}")).collect::<String>();
+        assert_eq!(stand_in.join().unwrap(), format!("AUTH EXTERNAL {hex_uid}"));
+    }
+
+    #[test]
+    fn rejected_auth_is_error() {
+        let (client, stand_in) = stand_in(|bus| {
+            bus.authenticate(false);
+        });
+
+        let err = BusConnection::from_stream(client).err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("authentication rejected: REJECTED EXTERNAL"),
+            "{err}"
+        );
+        stand_in.join().unwrap();
+    }
+
+    #[test]
+    fn hello_is_the_first_call() {
+        let (client, stand_in) = stand_in(|bus| bus.accept());
+
+        BusConnection::from_stream(client).unwrap();
+
+        let hello = stand_in.join().unwrap();
+        assert_eq!(hello.serial, 1);
+        assert_eq!(hello.fields[&FIELD_PATH], "/org/freedesktop/DBus");
+        assert_eq!(hello.fields[&FIELD_DESTINATION], "org.freedesktop.DBus");
+        assert_eq!(hello.fields[&FIELD_INTERFACE], "org.freedesktop.DBus");
+        assert_eq!(hello.fields[&FIELD_MEMBER], "Hello");
+        assert!(!hello.fields.contains_key(&FIELD_SIGNATURE));
+        assert!(hello.body.is_empty());
+    }
+
+    #[test]
+    fn notify_is_marshalled_with_padding() {
+        let (client, stand_in) = stand_in(|bus| {
+            bus.accept();
+            let notify = bus.read_call();
+            bus.reply(&notify, "u", &7u32.to_le_bytes());
+            notify
+        });
+
+        let config = NotificationConfig {
+            enabled: true,
+            on_success: true,
+            expire_timeout: 3000,
+            icon: "input-mouse".to_string(),
+        };
+        let mut bus = BusConnection::from_stream(client).unwrap();
+        let id = send_notification(&mut bus, &config, "Hi", "there", Urgency::Critical).unwrap();
+        assert_eq!(id, 7);
+
+        let notify = stand_in.join().unwrap();
+        assert_eq!(notify.serial, 2);
+        assert_eq!(notify.fields[&FIELD_MEMBER], "Notify");
+        assert_eq!(
+            notify.fields[&FIELD_INTERFACE],
+            "org.freedesktop.Notifications"
+        );
+        assert_eq!(notify.fields[&FIELD_SIGNATURE], "susssasa{sv}i");
+
+        let mut expected = Vec::new();
+        // s app_name
+        expected.extend([6, 0, 0, 0]);
+        expected.extend(b"wlrune\0");
+        expected.extend([0]);
+        // u replaces_id
+        expected.extend([0, 0, 0, 0]);
+        // s app_icon, already aligned
+        expected.extend([11, 0, 0, 0]);
+        expected.extend(b"input-mouse\0");
+        // s summary
+        expected.extend([2, 0, 0, 0]);
+        expected.extend(b"Hi\0");
+        expected.extend([0]);
+        // s body
+        expected.extend([5, 0, 0, 0]);
+        expected.extend(b"there\0");
+        expected.extend([0, 0]);
+        // as actions, empty
+        expected.extend([0, 0, 0, 0]);
+        // a{sv} hints, length doesn't include padding before the first entry
+        expected.extend([16, 0, 0, 0]);
+        expected.extend([0, 0, 0, 0]);
+        expected.extend([7, 0, 0, 0]);
+        expected.extend(b"urgency\0");
+        expected.extend([1, b'y', 0, 2]);
+        // i expire_timeout
+        expected.extend(3000i32.to_le_bytes());
+
+        assert_eq!(notify.body, expected);
+    }
+
+    #[test]
+    fn error_reply_is_returned_as_error() {
+        let (client, stand_in) = stand_in(|bus| {
+            bus.accept();
+            let call = bus.read_call();
+            bus.reply_error(
+                &call,
+                "org.freedesktop.DBus.Error.ServiceUnknown",
+                "The name is not activatable",
+            );
+        });
+
+        let mut bus = BusConnection::from_stream(client).unwrap();
+        let err = bus
+            .call(
+                "org.freedesktop.Notifications",
+                "/org/freedesktop/Notifications",
+                "org.freedesktop.Notifications",
+                "GetServerInformation",
+                "",
+                &[],
+            )
+            .unwrap_err();
+        stand_in.join().unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "org.freedesktop.DBus.Error.ServiceUnknown: The name is not activatable"
+        );
+    }
+
+    #[test]
+    fn replies_to_other_calls_are_skipped() {
+        let (client, stand_in) = stand_in(|bus| {
+            bus.accept();
+            let call = bus.read_call();
+            let unrelated = Call {
+                serial: 100,
+                fields: HashMap::new(),
+                body: Vec::new(),
+            };
+            bus.reply(&unrelated, "u", &1u32.to_le_bytes());
+            bus.reply(&call, "u", &2u32.to_le_bytes());
+        });
+
+        let mut bus = BusConnection::from_stream(client).unwrap();
+        let reply = bus.call("a.b", "/a/b", "a.b", "C", "", &[]).unwrap();
+        stand_in.join().unwrap();
+
+        assert_eq!(reply, 2u32.to_le_bytes());
+    }
+
+    #[test]
+    fn address_escapes_are_decoded() {
+        assert_eq!(unescape("/run/user/1000/bus"), "/run/user/1000/bus");
+        assert_eq!(unescape("/tmp/dbus%2dtest"), "/tmp/dbus-test");
+        // escape at the very end of the value
+        assert_eq!(unescape("/tmp/bus%2d"), "/tmp/bus-");
+        // broken escapes are left as is
+        assert_eq!(unescape("/tmp/bus%2"), "/tmp/bus%2");
+        assert_eq!(unescape("/tmp/bus%zz"), "/tmp/bus%zz");
+    }
+}
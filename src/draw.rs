@@ -0,0 +1,195 @@
+use crate::{
+    font::{glyph, GLYPH_ADVANCE, GLYPH_HEIGHT},
+    recognizer::Point,
+};
+
+/// Premultiplied ARGB color (0xAARRGGBB)
+pub type Color = u32;
+
+pub const TRANSPARENT: Color = 0x00000000;
+pub const WHITE: Color = 0xFFFFFFFF;
+pub const GRAY: Color = 0xFF909090;
+pub const SHADE: Color = 0xC0101010;
+
+/// View over ARGB8888 buffer of layer surface
+///
+/// Drawing methods take logical coordinates, which are multiplied by scale of the output,
+/// so buffer can be allocated at device resolution.
+pub struct Canvas<'a> {
+    pixels: &'a mut [u8],
+    /// Buffer width in device pixels
+    width: i32,
+    /// Buffer height in device pixels
+    height: i32,
+    scale: f64,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(pixels: &'a mut [u8], width: u32, height: u32, scale: f64) -> Self {
+        assert!(pixels.len() >= (width * height * 4) as usize);
+
+        Canvas {
+            pixels,
+            width: width as i32,
+            height: height as i32,
+            scale,
+        }
+    }
+
+    /// Width in logical pixels
+    pub fn width(&self) -> i32 {
+        (self.width as f64 / self.scale).round() as i32
+    }
+
+    /// Height in logical pixels
+    pub fn height(&self) -> i32 {
+        (self.height as f64 / self.scale).round() as i32
+    }
+
+    fn to_device(&self, value: i32) -> i32 {
+        (value as f64 * self.scale).round() as i32
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        let bytes = color.to_le_bytes();
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&bytes);
+        }
+    }
+
+    /// Set single pixel of buffer, coordinates are in device pixels
+    pub fn put_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+
+        let offset = ((y * self.width + x) * 4) as usize;
+        self.pixels[offset..offset + 4].copy_from_slice(&color.to_le_bytes());
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: Color) {
+        let (x0, y0) = (self.to_device(x), self.to_device(y));
+        let (x1, y1) = (self.to_device(x + width), self.to_device(y + height));
+
+        for py in y0.max(0)..y1.min(self.height) {
+            for px in x0.max(0)..x1.min(self.width) {
+                self.put_pixel(px, py, color);
+            }
+        }
+    }
+
+    pub fn stroke_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        thickness: i32,
+        color: Color,
+    ) {
+        self.fill_rect(x, y, width, thickness, color);
+        self.fill_rect(x, y + height - thickness, width, thickness, color);
+        self.fill_rect(x, y, thickness, height, color);
+        self.fill_rect(x + width - thickness, y, thickness, height, color);
+    }
+
+    /// Draw text with built-in font, every font pixel becomes `scale`x`scale` square
+    pub fn draw_text(&mut self, x: i32, y: i32, scale: i32, text: &str, color: Color) {
+        for (index, c) in text.chars().enumerate() {
+            let glyph_x = x + index as i32 * GLYPH_ADVANCE * scale;
+            for (column, bits) in glyph(c).iter().enumerate() {
+                for row in 0..GLYPH_HEIGHT {
+                    if bits & (1 << row) != 0 {
+                        self.fill_rect(
+                            glyph_x + column as i32 * scale,
+                            y + row * scale,
+                            scale,
+                            scale,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Line made of `thickness`x`thickness` squares
+    pub fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), thickness: i32, color: Color) {
+        let (mut x, mut y) = from;
+        let delta_x = (to.0 - x).abs();
+        let delta_y = -(to.1 - y).abs();
+        let step_x = if x < to.0 { 1 } else { -1 };
+        let step_y = if y < to.1 { 1 } else { -1 };
+        let mut error = delta_x + delta_y;
+        let offset = thickness / 2;
+
+        loop {
+            self.fill_rect(x - offset, y - offset, thickness, thickness, color);
+            if (x, y) == to {
+                break;
+            }
+
+            let doubled_error = 2 * error;
+            if doubled_error >= delta_y {
+                error += delta_y;
+                x += step_x;
+            }
+            if doubled_error <= delta_x {
+                error += delta_x;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Rasterize pattern path scaled to fit `size`x`size` box with preserved aspect ratio,
+    /// start of the path is marked with square
+    pub fn draw_pattern(&mut self, path: &[Point], x: i32, y: i32, size: i32, color: Color) {
+        const THICKNESS: i32 = 2;
+        const START_MARK: i32 = 6;
+
+        let Some(first) = path.first() else {
+            return;
+        };
+
+        let (mut min, mut max) = (*first, *first);
+        for point in path {
+            min.x = min.x.min(point.x);
+            min.y = min.y.min(point.y);
+            max.x = max.x.max(point.x);
+            max.y = max.y.max(point.y);
+        }
+
+        let extent = f64::max(max.x - min.x, max.y - min.y);
+        let box_size = (size - START_MARK) as f64;
+        let scale = if extent > 0.0 { box_size / extent } else { 0.0 };
+        // center path inside of the box
+        let shift_x = (box_size - (max.x - min.x) * scale) / 2.0;
+        let shift_y = (box_size - (max.y - min.y) * scale) / 2.0;
+        let margin = START_MARK / 2;
+
+        let to_canvas = |point: &Point| {
+            (
+                x + margin + ((point.x - min.x) * scale + shift_x).round() as i32,
+                y + margin + ((point.y - min.y) * scale + shift_y).round() as i32,
+            )
+        };
+
+        for segment in path.windows(2) {
+            self.draw_line(
+                to_canvas(&segment[0]),
+                to_canvas(&segment[1]),
+                THICKNESS,
+                color,
+            );
+        }
+
+        let (start_x, start_y) = to_canvas(first);
+        self.fill_rect(
+            start_x - START_MARK / 2,
+            start_y - START_MARK / 2,
+            START_MARK,
+            START_MARK,
+            color,
+        );
+    }
+}
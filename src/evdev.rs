@@ -0,0 +1,584 @@
+use std::{
+    collections::HashSet,
+    ffi::c_long,
+    fs::{read_to_string, File},
+    io::{ErrorKind, Read},
+    mem::size_of,
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use cgmath::MetricSpace;
+
+use crate::{
+    config::{CancelTrigger, CaptureConfig, EndTrigger, StartTrigger},
+    patterns::PointerGesture,
+    recognizer::Point,
+    wayland::CancelReason,
+};
+
+/// Size of timestamp in `struct input_event`, it's two longs on both 32 and 64-bit systems
+const INPUT_EVENT_TIME_SIZE: usize = 2 * size_of::<c_long>();
+/// Size of `struct input_event`: timestamp, type, code and value
+const INPUT_EVENT_SIZE: usize = INPUT_EVENT_TIME_SIZE + 8;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const SYN_REPORT: u16 = 0x00;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+
+/// Button codes of mice (BTN_MOUSE..BTN_JOYSTICK), keyboard keys are ignored
+const MOUSE_BUTTONS: std::ops::Range<u16> = 0x110..0x120;
+
+#[derive(Debug, Clone, Copy)]
+struct InputEvent {
+    kind: u16,
+    code: u16,
+    value: i32,
+}
+
+impl InputEvent {
+    fn from_bytes(buf: &[u8; INPUT_EVENT_SIZE]) -> Self {
+        let fields = &buf[INPUT_EVENT_TIME_SIZE..];
+        InputEvent {
+            kind: u16::from_ne_bytes([fields[0], fields[1]]),
+            code: u16::from_ne_bytes([fields[2], fields[3]]),
+            value: i32::from_ne_bytes([fields[4], fields[5], fields[6], fields[7]]),
+        }
+    }
+}
+
+/// Captures gesture from relative motion of mice, without overlay and compositor help
+///
+/// Every device is read by its own thread, because there is no poll in std.
+pub struct EvdevCapture {
+    events: Receiver<InputEvent>,
+    capture: CaptureConfig,
+    pointer_gestures: HashSet<PointerGesture>,
+    cancel_reason: Option<CancelReason>,
+    pointer_gesture: Option<PointerGesture>,
+    /// Button held at launch started the first gesture, the next ones start with a press
+    held_start_used: bool,
+}
+
+impl EvdevCapture {
+    pub fn new(capture: &CaptureConfig) -> Result<Self, ()> {
+        if capture.end_on.contains(&EndTrigger::ModifierRelease) {
+            eprintln!("ERROR: modifier_release end trigger is not supported by evdev backend");
+            return Err(());
+        }
+
+        let raw_devices = read_to_string("/proc/bus/input/devices").map_err(|err| {
+            eprintln!("ERROR: couldn't read /proc/bus/input/devices: {err}");
+        })?;
+
+        let device_paths = pointer_devices(&raw_devices);
+        if device_paths.is_empty() {
+            eprintln!("ERROR: there are no mice in /proc/bus/input/devices");
+            return Err(());
+        }
+
+        let (sender, events) = channel();
+        let mut denied = Vec::new();
+        let mut opened = 0;
+        for path in device_paths {
+            let mut device = match File::open(&path) {
+                Ok(device) => device,
+                Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                    denied.push(path);
+                    continue;
+                }
+                Err(err) => {
+                    eprintln!("WARNING: couldn't open {}: {}", path.display(), err);
+                    continue;
+                }
+            };
+
+            opened += 1;
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let mut buf = [0; INPUT_EVENT_SIZE];
+                while device.read_exact(&mut buf).is_ok() {
+                    if sender.send(InputEvent::from_bytes(&buf)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        if opened == 0 {
+            let paths = denied
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            eprintln!("ERROR: no permission to read {paths}");
+            eprintln!("evdev backend needs read access to input devices, usually it's given by membership in `input` group:");
+            eprintln!("    sudo usermod -aG input $USER");
+            eprintln!("and log in again");
+            return Err(());
+        }
+
+        for path in &denied {
+            eprintln!("WARNING: no permission to read {}, skipped", path.display());
+        }
+
+        Ok(EvdevCapture {
+            events,
+            capture: capture.clone(),
+            pointer_gestures: HashSet::new(),
+            cancel_reason: None,
+            pointer_gesture: None,
+            held_start_used: false,
+        })
+    }
+
+    /// Wheel and rocker gestures bound to commands
+    pub fn set_pointer_gestures(&mut self, gestures: HashSet<PointerGesture>) {
+        self.pointer_gestures = gestures;
+    }
+
+    pub fn pointer_gesture(&self) -> Option<PointerGesture> {
+        self.pointer_gesture
+    }
+
+    pub fn cancel_reason(&self) -> Option<CancelReason> {
+        self.cancel_reason
+    }
+
+    /// Wait for the next gesture, path is built from relative motion starting at (0, 0)
+    pub fn next_gesture(&mut self) -> Option<Vec<Point>> {
+        self.cancel_reason = None;
+        self.pointer_gesture = None;
+
+        // motion before the call isn't part of the gesture
+        while self.events.try_recv().is_ok() {}
+
+        self.read_gesture()
+    }
+
+    /// Build gesture from events received after the call
+    fn read_gesture(&mut self) -> Option<Vec<Point>> {
+        // button was released at the end of the previous sample
+        let held = self.capture.start_on == StartTrigger::Held && !self.held_start_used;
+        self.held_start_used = true;
+        let mut stroke = Stroke::new(held);
+        let (mut dx, mut dy) = (0, 0);
+
+        loop {
+            let event = match self.deadline(&stroke) {
+                None => self.events.recv().ok()?,
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    match self.events.recv_timeout(timeout) {
+                        Ok(event) => event,
+                        Err(RecvTimeoutError::Timeout) => return Some(stroke.path),
+                        Err(RecvTimeoutError::Disconnected) => return None,
+                    }
+                }
+            };
+
+            match (event.kind, event.code) {
+                (EV_REL, REL_X) => dx += event.value,
+                (EV_REL, REL_Y) => dy += event.value,
+                (EV_REL, REL_WHEEL | REL_HWHEEL) if stroke.drawing => {
+                    let gesture = match (event.code, event.value.signum()) {
+                        (REL_WHEEL, 1) => PointerGesture::WheelUp,
+                        (REL_WHEEL, -1) => PointerGesture::WheelDown,
+                        (REL_HWHEEL, 1) => PointerGesture::WheelRight,
+                        (REL_HWHEEL, -1) => PointerGesture::WheelLeft,
+                        _ => continue,
+                    };
+                    if self.finish_pointer_gesture(gesture) {
+                        return Some(stroke.path);
+                    }
+
+                    if self.capture.cancel_on.contains(&CancelTrigger::Scroll) {
+                        return self.cancel(CancelReason::Scroll);
+                    }
+                }
+                (EV_KEY, button) if MOUSE_BUTTONS.contains(&button) => {
+                    let button = button as u32;
+                    let is_trigger = self
+                        .capture
+                        .button
+                        .is_none_or(|trigger| trigger.code() == button);
+
+                    match event.value {
+                        // press
+                        // only trigger button starts stroke
+                        1 if !stroke.drawing => {
+                            stroke = Stroke::new(is_trigger);
+                            stroke.button = Some(button).filter(|_| is_trigger);
+                        }
+                        1 => {
+                            let rocker = PointerGesture::rocker(button);
+                            if rocker.is_some_and(|rocker| self.finish_pointer_gesture(rocker)) {
+                                return Some(stroke.path);
+                            }
+
+                            if self.capture.end_on.contains(&EndTrigger::SecondClick) && is_trigger
+                            {
+                                return Some(stroke.path);
+                            }
+
+                            let is_stroke_button = stroke.button == Some(button)
+                                || self
+                                    .capture
+                                    .button
+                                    .is_some_and(|trigger| trigger.code() == button);
+                            if !is_stroke_button
+                                && self
+                                    .capture
+                                    .cancel_on
+                                    .contains(&CancelTrigger::SecondButton)
+                            {
+                                return self.cancel(CancelReason::SecondButton);
+                            }
+                        }
+                        // release
+                        0 if stroke.drawing
+                            && is_trigger
+                            && self.capture.end_on.contains(&EndTrigger::Release) =>
+                        {
+                            return Some(stroke.path);
+                        }
+                        _ => {}
+                    }
+                }
+                (EV_SYN, SYN_REPORT) => {
+                    if (dx, dy) == (0, 0) || !stroke.drawing {
+                        (dx, dy) = (0, 0);
+                        continue;
+                    }
+
+                    let last = stroke.path.last().copied().unwrap_or(Point::new(0.0, 0.0));
+                    let point = Point::new(last.x + dx as f64, last.y + dy as f64);
+                    (dx, dy) = (0, 0);
+
+                    if let Some(reason) = stroke.push_point(point, &self.capture) {
+                        return self.cancel(reason);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Same timeouts as in wayland backend, counted from the stroke start
+    fn deadline(&self, stroke: &Stroke) -> Option<Instant> {
+        if !stroke.drawing {
+            return None;
+        }
+
+        let idle_deadline = self
+            .capture
+            .idle_timeout
+            .and_then(|timeout| Duration::try_from_secs_f64(timeout).ok())
+            .map(|timeout| stroke.last_motion + timeout);
+        let max_deadline = self
+            .capture
+            .max_duration
+            .and_then(|duration| Duration::try_from_secs_f64(duration).ok())
+            .map(|duration| stroke.started + duration);
+
+        match (idle_deadline, max_deadline) {
+            (Some(idle), Some(max)) => Some(idle.min(max)),
+            (idle, max) => idle.or(max),
+        }
+    }
+
+    fn finish_pointer_gesture(&mut self, gesture: PointerGesture) -> bool {
+        if !self.pointer_gestures.contains(&gesture) {
+            return false;
+        }
+
+        self.pointer_gesture = Some(gesture);
+        true
+    }
+
+    fn cancel(&mut self, reason: CancelReason) -> Option<Vec<Point>> {
+        self.cancel_reason = Some(reason);
+        None
+    }
+}
+
+/// Gesture path being drawn
+struct Stroke {
+    drawing: bool,
+    /// Button pressed to start the stroke, `None` if it was held before capture
+    button: Option<u32>,
+    path: Vec<Point>,
+    path_length: f64,
+    /// Pointer went far enough from the start point to come back to it
+    left_start: bool,
+    started: Instant,
+    last_motion: Instant,
+}
+
+impl Stroke {
+    fn new(drawing: bool) -> Self {
+        Stroke {
+            drawing,
+            button: None,
+            path: if drawing {
+                vec![Point::new(0.0, 0.0)]
+            } else {
+                Vec::new()
+            },
+            path_length: 0.0,
+            left_start: false,
+            started: Instant::now(),
+            last_motion: Instant::now(),
+        }
+    }
+
+    /// Add point to the path, returns reason if gesture should be cancelled
+    fn push_point(&mut self, point: Point, capture: &CaptureConfig) -> Option<CancelReason> {
+        if let Some(last) = self.path.last() {
+            self.path_length += last.distance(point);
+        }
+        self.path.push(point);
+        self.last_motion = Instant::now();
+
+        if capture
+            .max_path_length
+            .is_some_and(|max_length| self.path_length > max_length)
+        {
+            return Some(CancelReason::MaxPathLength);
+        }
+
+        if capture.cancel_on.contains(&CancelTrigger::ReturnToStart) {
+            let radius = capture.return_radius;
+            let distance = self.path[0].distance(point);
+            if distance > 2.0 * radius {
+                self.left_start = true;
+            } else if self.left_start && distance < radius {
+                return Some(CancelReason::ReturnToStart);
+            }
+        }
+
+        None
+    }
+}
+
+/// Event devices of mice listed in /proc/bus/input/devices, that is ones with
+/// relative X and Y axes
+fn pointer_devices(raw_devices: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    for device in raw_devices.split("\n\n") {
+        let mut event_handler = None;
+        let mut has_axes = false;
+
+        for line in device.lines() {
+            if let Some(handlers) = line.strip_prefix("H: Handlers=") {
+                event_handler = handlers
+                    .split_whitespace()
+                    .find(|handler| handler.starts_with("event"));
+            }
+
+            // bitmask is split into words, the lowest one is the last
+            if let Some(mask) = line.strip_prefix("B: REL=") {
+                has_axes = mask
+                    .split_whitespace()
+                    .last()
+                    .and_then(|word| u64::from_str_radix(word, 16).ok())
+                    .is_some_and(|bits| bits & 0b11 == 0b11);
+            }
+        }
+
+        if let Some(handler) = event_handler.filter(|_| has_axes) {
+            paths.push(PathBuf::from("/dev/input").join(handler));
+        }
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::Sender;
+
+    use super::*;
+
+    const DEVICES: &str = "\
+I: Bus=0011 Vendor=0001 Product=0001 Version=ab41
+N: Name=\"AT Translated Set 2 keyboard\"
+P: Phys=isa0060/serio0/input0
+S: Sysfs=/devices/platform/i8042/serio0/input/input3
+U: Uniq=
+H: Handlers=sysrq kbd leds event3
+B: PROP=0
+B: EV=120013
+B: KEY=402000000 3803078f800d001 feffffdfffefffff fffffffffffffffe
+B: MSC=10
+B: LED=7
+
+I: Bus=0003 Vendor=046d Product=c077 Version=0111
+N: Name=\"Logitech USB Optical Mouse\"
+P: Phys=usb-0000:00:14.0-2/input0
+S: Sysfs=/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0/0003:046D:C077.0001/input/input9
+U: Uniq=
+H: Handlers=mouse0 event9
+B: PROP=0
+B: EV=17
+B: KEY=ff0000 0 0 0 0
+B: REL=1943
+B: MSC=10
+
+I: Bus=0003 Vendor=0b05 Product=1866 Version=0110
+N: Name=\"ASUSTeK Computer Inc. N-KEY Device Consumer Control\"
+P: Phys=usb-0000:00:14.0-3/input2
+S: Sysfs=/devices/pci0000:00/0000:00:14.0/usb1/1-3/1-3:1.2/0003:0B05:1866.0003/input/input12
+U: Uniq=
+H: Handlers=kbd event12
+B: PROP=0
+B: EV=1f
+B: KEY=3f000303ff 0 0 483ffff17aff32d bfd4444600000000 1 130c730b17c000 267bfad9415fed 9e168000004400 10000002
+B: REL=1040
+B: ABS=100000000
+B: MSC=10
+
+I: Bus=0018 Vendor=04f3 Product=3140 Version=0100
+N: Name=\"ELAN1200:00 04F3:3140 Mouse\"
+P: Phys=i2c-ELAN1200:00
+S: Sysfs=/devices/pci0000:00/0000:00:15.0/i2c_designware.0/i2c-1/i2c-ELAN1200:00/0018:04F3:3140.0004/input/input15
+U: Uniq=
+H: Handlers=mouse1 event15
+B: PROP=0
+B: EV=17
+B: KEY=30000 0 0 0 0
+B: REL=3
+B: MSC=10
+";
+
+    #[test]
+    fn devices_with_both_relative_axes_are_picked() {
+        assert_eq!(
+            pointer_devices(DEVICES),
+            vec![
+                PathBuf::from("/dev/input/event9"),
+                PathBuf::from("/dev/input/event15"),
+            ]
+        );
+    }
+
+    #[test]
+    fn device_without_event_handler_is_skipped() {
+        let raw = "N: Name=\"PS/2 Generic Mouse\"\nH: Handlers=mouse2\nB: REL=3\n";
+        assert!(pointer_devices(raw).is_empty());
+    }
+
+    /// Event as the kernel writes it, timestamp is left zero
+    fn event_bytes(kind: u16, code: u16, value: i32) -> [u8; INPUT_EVENT_SIZE] {
+        let mut buf = [0; INPUT_EVENT_SIZE];
+        buf[INPUT_EVENT_TIME_SIZE..INPUT_EVENT_TIME_SIZE + 2].copy_from_slice(&kind.to_ne_bytes());
+        buf[INPUT_EVENT_TIME_SIZE + 2..INPUT_EVENT_TIME_SIZE + 4]
+            .copy_from_slice(&code.to_ne_bytes());
+        buf[INPUT_EVENT_TIME_SIZE + 4..].copy_from_slice(&value.to_ne_bytes());
+        buf
+    }
+
+    fn send(sender: &Sender<InputEvent>, kind: u16, code: u16, value: i32) {
+        let event = InputEvent::from_bytes(&event_bytes(kind, code, value));
+        sender.send(event).unwrap();
+    }
+
+    fn capture(events: Receiver<InputEvent>) -> EvdevCapture {
+        EvdevCapture {
+            events,
+            capture: CaptureConfig::default(),
+            pointer_gestures: HashSet::new(),
+            cancel_reason: None,
+            pointer_gesture: None,
+            held_start_used: false,
+        }
+    }
+
+    const BTN_LEFT: u16 = 0x110;
+
+    #[test]
+    fn event_is_decoded_after_timestamp() {
+        let event = InputEvent::from_bytes(&event_bytes(EV_REL, REL_Y, -3));
+        assert_eq!((event.kind, event.code, event.value), (EV_REL, REL_Y, -3));
+    }
+
+    #[test]
+    fn motion_is_accumulated_until_sync() {
+        let (sender, events) = channel();
+        send(&sender, EV_REL, REL_X, 4);
+        send(&sender, EV_REL, REL_X, 6);
+        send(&sender, EV_REL, REL_Y, -2);
+        send(&sender, EV_SYN, SYN_REPORT, 0);
+        // report without motion doesn't add point
+        send(&sender, EV_SYN, SYN_REPORT, 0);
+        send(&sender, EV_REL, REL_Y, 5);
+        send(&sender, EV_SYN, SYN_REPORT, 0);
+        send(&sender, EV_KEY, BTN_LEFT, 0);
+
+        let path = capture(events).read_gesture().unwrap();
+        assert_eq!(
+            path,
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(10.0, -2.0),
+                Point::new(10.0, 3.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn bound_wheel_finishes_gesture() {
+        let (sender, events) = channel();
+        send(&sender, EV_REL, REL_X, 7);
+        send(&sender, EV_SYN, SYN_REPORT, 0);
+        send(&sender, EV_REL, REL_WHEEL, -1);
+
+        let mut capture = capture(events);
+        capture.set_pointer_gestures(HashSet::from([PointerGesture::WheelDown]));
+        let path = capture.read_gesture().unwrap();
+        assert_eq!(path, vec![Point::new(0.0, 0.0), Point::new(7.0, 0.0)]);
+        assert_eq!(capture.pointer_gesture(), Some(PointerGesture::WheelDown));
+    }
+
+    #[test]
+    fn next_held_sample_waits_for_press() {
+        let (sender, events) = channel();
+        let mut capture = capture(events);
+        send(&sender, EV_REL, REL_X, 5);
+        send(&sender, EV_SYN, SYN_REPORT, 0);
+        send(&sender, EV_KEY, BTN_LEFT, 0);
+        assert_eq!(
+            capture.read_gesture().unwrap(),
+            vec![Point::new(0.0, 0.0), Point::new(5.0, 0.0)]
+        );
+
+        // hover before the press isn't part of the sample
+        send(&sender, EV_REL, REL_X, 40);
+        send(&sender, EV_SYN, SYN_REPORT, 0);
+        send(&sender, EV_KEY, BTN_LEFT, 1);
+        send(&sender, EV_REL, REL_Y, 3);
+        send(&sender, EV_SYN, SYN_REPORT, 0);
+        send(&sender, EV_KEY, BTN_LEFT, 0);
+        assert_eq!(
+            capture.read_gesture().unwrap(),
+            vec![Point::new(0.0, 0.0), Point::new(0.0, 3.0)]
+        );
+    }
+
+    #[test]
+    fn closed_devices_end_capture() {
+        let (sender, events) = channel();
+        send(&sender, EV_REL, REL_X, 7);
+        drop(sender);
+
+        assert_eq!(capture(events).read_gesture(), None);
+    }
+}
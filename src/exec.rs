@@ -0,0 +1,313 @@
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::{net::UnixDatagram, process::CommandExt},
+    process::{Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rustix::process::{kill_process_group, Pid, Signal};
+
+use crate::{
+    config::{AppConfig, CommandLine, CommandOutput, GestureCommand},
+    notify::{notify, Urgency},
+};
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// syslog priorities used for journal entries
+const PRIORITY_ERR: u8 = 3;
+const PRIORITY_INFO: u8 = 6;
+
+/// Run command bound to gesture
+///
+/// By default command detached and its output discarded. When binding asks for output,
+/// timeout or failure report, wlrune waits for command to finish.
+pub fn run_command(config: &AppConfig, gesture_command: &GestureCommand) -> Result<(), ()> {
+    let mut cmd = build_command(config, gesture_command)?;
+    cmd.stdin(Stdio::null());
+
+    if !gesture_command.is_supervised() {
+        cmd.stderr(Stdio::null());
+        cmd.stdout(Stdio::null());
+
+        let _child = spawn(&mut cmd, gesture_command)?;
+        return Ok(());
+    }
+
+    let timeout = match gesture_command.timeout {
+        Some(timeout) => Some(Duration::try_from_secs_f64(timeout).map_err(|err| {
+            eprintln!(
+                "ERROR: invalid timeout for pattern {}: {}",
+                gesture_command.pattern, err
+            );
+        })?),
+        None => None,
+    };
+
+    let sink = match &gesture_command.output {
+        Some(output) => Some(Arc::new(Mutex::new(OutputSink::open(
+            output,
+            &gesture_command.pattern,
+        )?))),
+        None => None,
+    };
+
+    if sink.is_some() {
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+    } else {
+        cmd.stdout(Stdio::null());
+        cmd.stderr(Stdio::null());
+    }
+
+    // own process group, so timeout kills children of the shell along with it
+    cmd.process_group(0);
+    let mut child = spawn(&mut cmd, gesture_command)?;
+
+    let mut forwarders = Vec::new();
+    if let Some(sink) = &sink {
+        if let Some(stdout) = child.stdout.take() {
+            forwarders.push(forward_output(stdout, sink.clone(), PRIORITY_INFO));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forwarders.push(forward_output(stderr, sink.clone(), PRIORITY_ERR));
+        }
+    }
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("ERROR: failed to wait for command: {err}");
+                return Err(());
+            }
+        }
+
+        if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+            // child isn't reaped yet, so its group id can't be reused
+            let _ = kill_process_group(Pid::from_child(&child), Signal::Kill);
+            let _ = child.wait();
+            break None;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    // output of killed command may be still held by its children, don't wait for them
+    if status.is_some() {
+        for forwarder in forwarders {
+            let _ = forwarder.join();
+        }
+    }
+
+    let failure = match status {
+        Some(status) if status.success() => return Ok(()),
+        Some(status) => describe_status(status),
+        None => format!(
+            "killed after timeout ({:.1}s)",
+            started.elapsed().as_secs_f64()
+        ),
+    };
+
+    let message = format!(
+        "command for pattern {} {}",
+        gesture_command.pattern, failure
+    );
+    eprintln!("ERROR: {message}");
+    notify(
+        &config.notifications,
+        "Gesture command failed",
+        &message,
+        Urgency::Critical,
+    );
+    if let Some(sink) = &sink {
+        sink.lock()
+            .unwrap()
+            .write_line(&format!("wlrune: {message}"), PRIORITY_ERR);
+    }
+
+    Err(())
+}
+
+fn build_command(config: &AppConfig, gesture_command: &GestureCommand) -> Result<Command, ()> {
+    let mut cmd = match &gesture_command.command {
+        CommandLine::Shell(raw_command) => {
+            let shell = config
+                .shell
+                .clone()
+                .or_else(|| env::var("SHELL").ok().filter(|shell| !shell.is_empty()))
+                .unwrap_or_else(|| "sh".to_string());
+
+            let mut cmd = Command::new(shell);
+            cmd.arg("-c");
+            cmd.arg(raw_command);
+            cmd
+        }
+        CommandLine::Argv(argv) => {
+            let Some((program, args)) = argv.split_first() else {
+                eprintln!(
+                    "ERROR: command for pattern {} is empty",
+                    gesture_command.pattern
+                );
+                return Err(());
+            };
+
+            let mut cmd = Command::new(program);
+            cmd.args(args);
+            cmd
+        }
+    };
+
+    if let Some(cwd) = &gesture_command.cwd {
+        cmd.current_dir(cwd);
+    }
+    cmd.envs(&gesture_command.env);
+
+    Ok(cmd)
+}
+
+fn spawn(cmd: &mut Command, gesture_command: &GestureCommand) -> Result<std::process::Child, ()> {
+    cmd.spawn().map_err(|err| {
+        eprintln!(
+            "ERROR: failed to run command for pattern {}: {}",
+            gesture_command.pattern, err
+        );
+    })
+}
+
+fn describe_status(status: ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited with status {code}"),
+        (None, Some(signal)) => format!("terminated by signal {signal}"),
+        (None, None) => format!("failed ({status})"),
+    }
+}
+
+fn forward_output<R: Read + Send + 'static>(
+    output: R,
+    sink: Arc<Mutex<OutputSink>>,
+    priority: u8,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let reader = BufReader::new(output);
+        for line in reader.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            sink.lock().unwrap().write_line(&line, priority);
+        }
+    })
+}
+
+enum OutputSink {
+    File(std::fs::File),
+    Journal {
+        socket: UnixDatagram,
+        pattern: String,
+    },
+}
+
+impl OutputSink {
+    fn open(output: &CommandOutput, pattern: &str) -> Result<Self, ()> {
+        match output {
+            CommandOutput::File(path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|err| {
+                        eprintln!("ERROR: couldn't open {}: {}", path.display(), err);
+                    })?;
+                Ok(OutputSink::File(file))
+            }
+            CommandOutput::Journal => {
+                let socket = UnixDatagram::unbound().map_err(|err| {
+                    eprintln!("ERROR: couldn't create journal socket: {err}");
+                })?;
+                socket.connect(JOURNAL_SOCKET).map_err(|err| {
+                    eprintln!("ERROR: couldn't connect to journal {JOURNAL_SOCKET}: {err}");
+                })?;
+                Ok(OutputSink::Journal {
+                    socket,
+                    pattern: pattern.to_string(),
+                })
+            }
+        }
+    }
+
+    /// Errors are ignored, losing a log line shouldn't affect command
+    fn write_line(&mut self, line: &str, priority: u8) {
+        match self {
+            OutputSink::File(file) => {
+                let _ = writeln!(file, "{line}");
+            }
+            OutputSink::Journal { socket, pattern } => {
+                // native journal protocol, values can't contain newlines in this simple form
+                let entry = format!(
+                    "SYSLOG_IDENTIFIER=wlrune\nPRIORITY={}\nWLRUNE_PATTERN={}\nMESSAGE={}\n",
+                    priority,
+                    pattern.replace('\n', " "),
+                    line.replace('\n', " ")
+                );
+                let _ = socket.send(entry.as_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    /// Process is gone or left as zombie until init reaps it
+    fn is_dead(pid: &str) -> bool {
+        match fs::read_to_string(format!("/proc/{pid}/stat")) {
+            Ok(stat) => stat
+                .rsplit_once(')')
+                .is_some_and(|(_, rest)| rest.trim_start().starts_with('Z')),
+            Err(_) => true,
+        }
+    }
+
+    #[test]
+    fn timeout_kills_children_of_shell() {
+        let dir = env::temp_dir().join(format!("wlrune-exec-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let pid_file = dir.join("pid");
+        let log = dir.join("log");
+
+        let config: AppConfig =
+            serde_yml::from_str("shell: sh\nnotifications:\n  enabled: false\n").unwrap();
+
+        let mut cmd = GestureCommand::new(
+            "test".to_string(),
+            format!("sleep 30 & echo $! > {}; wait", pid_file.display()),
+        );
+        cmd.timeout = Some(0.5);
+        cmd.output = Some(CommandOutput::File(log));
+
+        let started = Instant::now();
+        assert!(run_command(&config, &cmd).is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let pid = fs::read_to_string(&pid_file).unwrap();
+        let pid = pid.trim();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !is_dead(pid) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(is_dead(pid), "sleep {pid} outlived its shell");
+    }
+}
@@ -0,0 +1,458 @@
+use std::{
+    env,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    config::{print_commands, GestureCommand},
+    patterns::{store_gesture, validate_pattern_name, ConflictStrategy},
+    recognizer::Point,
+};
+
+/// Class names of easystroke actions that may appear in actions file
+const EASYSTROKE_ACTIONS: &[&str] = &[
+    "Command", "SendKey", "SendText", "Scroll", "Ignore", "Button", "Misc",
+];
+
+/// Import strokes from easystroke actions file (by default newest ~/.easystroke/actions*)
+///
+/// easystroke stores its database as boost text archive which layout depends on easystroke
+/// version, so the file is scanned for point lists and strings instead of being decoded
+/// exactly. Every stroke followed by two strings treated as command and its name, generated
+/// commands printed for review rather than written to config.
+pub fn import_easystroke(
+    actions_path: Option<&Path>,
+    on_conflict: ConflictStrategy,
+) -> Result<(), ()> {
+    let actions_path = match actions_path {
+        Some(actions_path) => actions_path.to_path_buf(),
+        None => default_easystroke_actions()?,
+    };
+
+    let raw = read_to_string(&actions_path).map_err(|err| {
+        eprintln!(
+            "ERROR: failed to read easystroke actions {}, {}",
+            actions_path.display(),
+            err
+        );
+    })?;
+
+    if !raw.contains("serialization::archive") {
+        eprintln!(
+            "ERROR: {} is not easystroke actions file (expected boost text archive)",
+            actions_path.display()
+        );
+        return Err(());
+    }
+
+    let records = scan_easystroke_archive(&raw);
+    if records.is_empty() {
+        eprintln!("ERROR: no strokes found in {}", actions_path.display());
+        return Err(());
+    }
+
+    let mut commands = Vec::new();
+    let mut imported = 0;
+    for (index, record) in records.iter().enumerate() {
+        let fallback = format!("easystroke-{}", index + 1);
+        let name = match &record.name {
+            Some(name) => imported_name(name, fallback),
+            None => fallback,
+        };
+
+        // strokes of the same easystroke action become samples of one pattern
+        let Some(stored_name) = store_gesture(&name, &record.strokes, on_conflict)? else {
+            continue;
+        };
+        imported += 1;
+
+        if let Some(command) = &record.command {
+            commands.push(GestureCommand::new(stored_name, command.clone()));
+        }
+    }
+
+    eprintln!("imported {} pattern(s)", imported);
+
+    if !commands.is_empty() {
+        print_commands(&commands)?;
+    }
+
+    Ok(())
+}
+
+/// Import gestures from JSON sample set in $1 recognizer format
+///
+/// Accepts list of gestures (or single gesture) `{"name": "...", "points": [...]}`, where
+/// points are `[x, y]` pairs or `{"x": .., "y": ..}` (`X`/`Y` also accepted) objects. Samples
+/// sharing same name saved as samples of one pattern
+pub fn import_dollar_one(samples_path: &Path, on_conflict: ConflictStrategy) -> Result<(), ()> {
+    let raw = read_to_string(samples_path).map_err(|err| {
+        eprintln!(
+            "ERROR: failed to read samples {}, {}",
+            samples_path.display(),
+            err
+        );
+    })?;
+
+    let samples: SampleSet = serde_yml::from_str(&raw).map_err(|err| {
+        eprintln!(
+            "ERROR: failed to parse samples {}, {}",
+            samples_path.display(),
+            err
+        );
+    })?;
+
+    let samples = match samples {
+        SampleSet::Many(samples) => samples,
+        SampleSet::One(sample) => vec![sample],
+    };
+
+    // several samples of the same gesture are common in datasets, keep all of them
+    let mut patterns: Vec<(String, Vec<Vec<Point>>)> = Vec::new();
+    for (index, sample) in samples.iter().enumerate() {
+        let name = imported_name(&sample.name, format!("sample-{}", index + 1));
+
+        let path = sample
+            .points
+            .iter()
+            .map(|point| match point {
+                SamplePoint::Pair([x, y]) => Point::new(*x, *y),
+                SamplePoint::Object { x, y } => Point::new(*x, *y),
+            })
+            .collect::<Vec<_>>();
+
+        match patterns.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, pattern_samples)) => pattern_samples.push(path),
+            None => patterns.push((name, vec![path])),
+        }
+    }
+
+    let mut imported = 0;
+    for (name, pattern_samples) in &patterns {
+        if store_gesture(name, pattern_samples, on_conflict)?.is_some() {
+            imported += 1;
+        }
+    }
+
+    eprintln!("imported {} pattern(s)", imported);
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SampleSet {
+    Many(Vec<SampleGesture>),
+    One(SampleGesture),
+}
+
+#[derive(Debug, Deserialize)]
+struct SampleGesture {
+    #[serde(default, alias = "Name")]
+    name: String,
+    #[serde(alias = "Points")]
+    points: Vec<SamplePoint>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SamplePoint {
+    Pair([f64; 2]),
+    Object {
+        #[serde(alias = "X")]
+        x: f64,
+        #[serde(alias = "Y")]
+        y: f64,
+    },
+}
+
+#[derive(Debug, Default)]
+struct EasystrokeRecord {
+    strokes: Vec<Vec<Point>>,
+    name: Option<String>,
+    command: Option<String>,
+}
+
+enum ArchiveItem {
+    Number(f64),
+    Text(String),
+}
+
+fn scan_easystroke_archive(raw: &str) -> Vec<EasystrokeRecord> {
+    let items = tokenize_archive(raw);
+
+    let mut records = Vec::new();
+    let mut strokes = Vec::new();
+    let mut texts: Vec<String> = Vec::new();
+
+    let mut index = 0;
+    while index < items.len() {
+        if let Some((stroke, next_index)) = read_stroke(&items, index) {
+            if !strokes.is_empty() && !texts.is_empty() {
+                records.push(easystroke_record(std::mem::take(&mut strokes), &texts));
+            }
+            // strings before the first stroke of record belong to archive header
+            if strokes.is_empty() {
+                texts.clear();
+            }
+
+            strokes.push(stroke);
+            index = next_index;
+            continue;
+        }
+
+        if let ArchiveItem::Text(text) = &items[index] {
+            texts.push(text.clone());
+        }
+        index += 1;
+    }
+
+    if !strokes.is_empty() {
+        records.push(easystroke_record(strokes, &texts));
+    }
+
+    records
+}
+
+fn easystroke_record(strokes: Vec<Vec<Point>>, texts: &[String]) -> EasystrokeRecord {
+    let is_text_action = texts.iter().any(|text| text == "SendText");
+    let texts = texts
+        .iter()
+        .filter(|text| !EASYSTROKE_ACTIONS.contains(&text.as_str()))
+        .collect::<Vec<_>>();
+
+    let mut record = EasystrokeRecord {
+        strokes,
+        ..Default::default()
+    };
+
+    match texts.as_slice() {
+        [] => {}
+        [name] => record.name = Some(name.to_string()),
+        [payload, .., name] => {
+            record.name = Some(name.to_string());
+            if !is_text_action {
+                record.command = Some(payload.to_string());
+            }
+        }
+    }
+
+    record
+}
+
+/// Try to read point list (`count [header...] x y [t] x y [t] ...`) starting at `index`
+fn read_stroke(items: &[ArchiveItem], index: usize) -> Option<(Vec<Point>, usize)> {
+    let count = match items[index] {
+        ArchiveItem::Number(count) if count.fract() == 0.0 && count >= 2.0 => count as usize,
+        _ => return None,
+    };
+
+    for header_len in 0..=3 {
+        for stride in [2, 3] {
+            let start = index + 1 + header_len;
+            let end = start + count * stride;
+            if end > items.len() {
+                continue;
+            }
+
+            // header of the list consists of small integers
+            let is_header = items[index + 1..start]
+                .iter()
+                .all(|item| matches!(item, ArchiveItem::Number(n) if n.fract() == 0.0));
+            if !is_header {
+                continue;
+            }
+
+            let mut points = Vec::with_capacity(count);
+            let mut fractional = 0;
+            for chunk in items[start..end].chunks(stride) {
+                let (ArchiveItem::Number(x), ArchiveItem::Number(y)) = (&chunk[0], &chunk[1])
+                else {
+                    break;
+                };
+                fractional += (x.fract() != 0.0) as usize + (y.fract() != 0.0) as usize;
+                points.push(Point::new(*x, *y));
+            }
+
+            // stroke is followed by its button (integer), anything else means wrong guess
+            let followed_by_integer = match items.get(end) {
+                Some(ArchiveItem::Number(n)) => n.fract() == 0.0,
+                Some(ArchiveItem::Text(_)) => false,
+                None => true,
+            };
+
+            if points.len() == count && fractional >= count && followed_by_integer {
+                return Some((points, end));
+            }
+        }
+    }
+
+    None
+}
+
+/// Split boost text archive to numbers and length prefixed strings
+fn tokenize_archive(raw: &str) -> Vec<ArchiveItem> {
+    let bytes = raw.as_bytes();
+    let mut items = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        if bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+
+        let start = pos;
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let token = &raw[start..pos];
+
+        if let Some(text) = length_prefixed_string(raw, token, pos) {
+            pos += 1 + text.len();
+            items.push(ArchiveItem::Text(text.to_string()));
+            continue;
+        }
+
+        match token.parse::<f64>() {
+            Ok(number) => items.push(ArchiveItem::Number(number)),
+            Err(_) => items.push(ArchiveItem::Text(token.to_string())),
+        }
+    }
+
+    items
+}
+
+/// String in boost text archive stored as `<length> <bytes>`, numbers are ambiguous with
+/// such strings, so only strings with non-numeric content accepted
+fn length_prefixed_string<'a>(raw: &'a str, token: &str, pos: usize) -> Option<&'a str> {
+    let len: usize = token.parse().ok()?;
+    let start = pos + 1;
+    let end = start + len;
+
+    if len == 0 || raw.as_bytes().get(pos) != Some(&b' ') || end > raw.len() {
+        return None;
+    }
+
+    let text = raw.get(start..end)?;
+    let is_terminated = raw[end..]
+        .chars()
+        .next()
+        .is_none_or(|c| c.is_ascii_whitespace());
+    let is_numeric = text
+        .split_ascii_whitespace()
+        .all(|part| part.parse::<f64>().is_ok());
+
+    if text.starts_with(char::is_whitespace) || !is_terminated || is_numeric {
+        return None;
+    }
+
+    Some(text)
+}
+
+/// Pattern name made of name from other tool, `fallback` if nothing usable left of it
+fn imported_name(name: &str, fallback: String) -> String {
+    let sanitized = sanitize_name(name);
+    if validate_pattern_name(&sanitized).is_err() {
+        if !name.is_empty() {
+            eprintln!(
+                "WARNING: {:?} can't be used as pattern name, saved as {}",
+                name, fallback
+            );
+        }
+        return fallback;
+    }
+
+    sanitized
+}
+
+fn sanitize_name(name: &str) -> String {
+    name.trim()
+        .trim_start_matches('.')
+        .chars()
+        .map(|c| {
+            if c == '/' || c.is_whitespace() {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+fn default_easystroke_actions() -> Result<PathBuf, ()> {
+    let home = env::var("HOME").map_err(|err| {
+        eprintln!("ERROR: coludn't find $HOME: {err}");
+    })?;
+    let easystroke_dir = Path::new(&home).join(".easystroke");
+
+    let entries = read_dir(&easystroke_dir).map_err(|err| {
+        eprintln!("ERROR: couldn't read {}: {}", easystroke_dir.display(), err);
+    })?;
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("actions"))
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+        .map(|entry| entry.path())
+        .ok_or_else(|| {
+            eprintln!(
+                "ERROR: easystroke actions file not found in {}",
+                easystroke_dir.display()
+            );
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn length_prefixed_strings_are_told_from_numbers() {
+        let items = tokenize_archive("2 up 3 0 1 14 xdotool key F5 4 1.5 2.5");
+        let items = items
+            .iter()
+            .map(|item| match item {
+                ArchiveItem::Number(n) => n.to_string(),
+                ArchiveItem::Text(text) => format!("{text:?}"),
+            })
+            .collect::<Vec<_>>();
+
+        // `4 1.5 2.5` would be a string of numbers, which is left as numbers
+        assert_eq!(
+            items,
+            [
+                "\"up\"",
+                "3",
+                "0",
+                "1",
+                "\"xdotool key F5\"",
+                "4",
+                "1.5",
+                "2.5"
+            ]
+        );
+    }
+
+    #[test]
+    fn stroke_is_read_with_and_without_timestamps() {
+        let with_time = tokenize_archive("3 0 0.5 0.1 0 0.5 0.5 0.1 0.5 0.9 0.2 3");
+        let (stroke, end) = read_stroke(&with_time, 0).unwrap();
+        assert_eq!(end, with_time.len() - 1);
+        assert_eq!(
+            stroke,
+            [
+                Point::new(0.5, 0.1),
+                Point::new(0.5, 0.5),
+                Point::new(0.5, 0.9)
+            ]
+        );
+
+        let without_time = tokenize_archive("2 0.25 0.75 0.75 0.25");
+        let (stroke, _) = read_stroke(&without_time, 0).unwrap();
+        assert_eq!(stroke, [Point::new(0.25, 0.75), Point::new(0.75, 0.25)]);
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+
+use crate::{
+    config::AppConfig,
+    patterns::{gesture_file_path, load_gesture, pattern_names, PointerGesture},
+    recognizer::{Unistroke, UnistrokeRecognizer},
+};
+
+/// Report patterns that recognizer can't tell apart and commands without pattern
+pub fn lint_patterns(config: &AppConfig) -> Result<(), ()> {
+    let recognizer_conf = &config.recognizer;
+    let recognizer = UnistrokeRecognizer::new(recognizer_conf);
+
+    let names = pattern_names()?;
+
+    let mut issue_count = 0;
+    let mut patterns = Vec::new();
+    for name in &names {
+        match load_gesture(&gesture_file_path(name)?) {
+            Ok(samples) => patterns.extend(samples.into_iter().map(|path| Unistroke {
+                name: name.clone(),
+                path,
+            })),
+            Err(reason) => {
+                eprintln!("malformed: pattern {} can't be loaded, {}", name, reason);
+                issue_count += 1;
+            }
+        }
+    }
+
+    let normalized = patterns
+        .iter()
+        .map(|unistroke| recognizer.normalize_stroke_path(&unistroke.path))
+        .collect::<Vec<_>>();
+
+    for (unistroke, normalized_path) in patterns.iter().zip(&normalized) {
+        if let Err(err) = normalized_path {
+            eprintln!(
+                "degenerate: sample of pattern {} is ignored, reason: {}",
+                unistroke.name,
+                err.description()
+            );
+            issue_count += 1;
+        }
+    }
+
+    // best similarity between samples of every pair of patterns
+    let mut collisions: Vec<(&str, &str, f64)> = Vec::new();
+    for (i, lhs) in patterns.iter().enumerate() {
+        for (j, rhs) in patterns.iter().enumerate().skip(i + 1) {
+            if lhs.name == rhs.name {
+                continue;
+            }
+
+            let (Ok(lhs_path), Ok(rhs_path)) = (&normalized[i], &normalized[j]) else {
+                continue;
+            };
+            let similarity = f64::max(
+                recognizer.similarity(lhs_path, rhs_path),
+                recognizer.similarity(rhs_path, lhs_path),
+            );
+            if similarity < recognizer_conf.command_execute_treshold {
+                continue;
+            }
+
+            let (first, second) = if lhs.name < rhs.name {
+                (lhs.name.as_str(), rhs.name.as_str())
+            } else {
+                (rhs.name.as_str(), lhs.name.as_str())
+            };
+
+            match collisions
+                .iter_mut()
+                .find(|(a, b, _)| *a == first && *b == second)
+            {
+                Some(collision) => collision.2 = collision.2.max(similarity),
+                None => collisions.push((first, second, similarity)),
+            }
+        }
+    }
+
+    collisions.sort_by(|lhs, rhs| rhs.2.total_cmp(&lhs.2));
+    for (first, second, similarity) in &collisions {
+        eprintln!(
+            "collision: patterns {} and {} are too similar (similarity ≈ {:.02}, treshold {})",
+            first, second, similarity, recognizer_conf.command_execute_treshold
+        );
+        issue_count += 1;
+    }
+
+    let existing = names.iter().map(String::as_str).collect::<HashSet<_>>();
+    let mut reported = HashSet::new();
+    for cmd in &config.commands {
+        let is_builtin = PointerGesture::from_name(&cmd.pattern).is_some();
+        if !is_builtin
+            && !existing.contains(cmd.pattern.as_str())
+            && reported.insert(cmd.pattern.as_str())
+        {
+            eprintln!(
+                "missing: pattern {} used in config but not recorded",
+                cmd.pattern
+            );
+            issue_count += 1;
+        }
+    }
+
+    if issue_count > 0 {
+        eprintln!(
+            "found {} issue(s) in {} pattern(s)",
+            issue_count,
+            names.len()
+        );
+        return Err(());
+    }
+
+    eprintln!("no issues found in {} pattern(s)", names.len());
+
+    Ok(())
+}
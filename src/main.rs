@@ -1,20 +1,43 @@
+mod capture;
+mod cheatsheet;
 mod config;
+mod config_check;
+mod dbus;
+mod draw;
+mod evdev;
+mod exec;
+mod font;
+mod import;
+mod lint;
+mod notify;
+mod pack;
+mod patterns;
 mod recognizer;
+mod record;
 mod wayland;
 
 use std::{
     collections::HashSet,
     env,
-    fs::{create_dir_all, File},
-    io::{BufRead, BufReader, Write},
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    sync::Arc,
+    time::Duration,
 };
 
+use capture::GestureSource;
+use cheatsheet::show_cheatsheet;
 use clap::Parser;
-use config::AppConfig;
-use recognizer::{degrees_to_radians, Point, Unistroke, UnistrokeRecognizer};
-use wayland::get_user_gesture;
+use config::{AppConfig, OverlayMode};
+use config_check::check_config;
+use exec::run_command;
+use import::{import_dollar_one, import_easystroke};
+use lint::lint_patterns;
+use notify::{notify, Urgency};
+use pack::{export_pack, import_pack};
+use patterns::{load_gestures, ConflictStrategy, PointerGesture};
+use recognizer::UnistrokeRecognizer;
+use record::record_pattern;
+use wayland::{bindings_for, LivePreview};
 
 /// Mouse gestures for wayland compositors
 #[derive(Parser, Debug)]
@@ -33,6 +56,20 @@ enum AppSubCommand {
     Recognize,
     /// Record pattern for recognition. By default saved to ~/.local/share/wlrune/patterns
     Record(RecordArguments),
+    /// Show configured patterns with their commands, close by click or Escape
+    Cheatsheet,
+    /// Share patterns (and related commands) as single file
+    #[clap(subcommand)]
+    Pack(PackSubCommand),
+    /// Import patterns from other gesture tools
+    #[clap(subcommand)]
+    Import(ImportSubCommand),
+    /// Manage recorded patterns
+    #[clap(subcommand)]
+    Pattern(PatternSubCommand),
+    /// Manage configuration file
+    #[clap(subcommand)]
+    Config(ConfigSubCommand),
 }
 
 #[derive(Parser, Debug)]
@@ -41,18 +78,101 @@ struct RecordArguments {
     name: String,
     #[arg(long = "force", short = 'f', default_value_t = false)]
     force: bool,
+    /// Number of strokes to draw in a row, inconsistent ones are rejected
+    #[arg(long = "samples", short = 's', default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    samples: u32,
+}
+
+#[derive(Parser, Debug)]
+enum PackSubCommand {
+    /// Export patterns to pack file. By default all recorded patterns exported
+    Export(PackExportArguments),
+    /// Import patterns from pack file
+    Import(PackImportArguments),
+}
+
+#[derive(Parser, Debug)]
+enum ConfigSubCommand {
+    /// Validate configuration file and report problems with their positions
+    Check,
+}
+
+#[derive(Parser, Debug)]
+enum PatternSubCommand {
+    /// Report too similar patterns and patterns missing for configured commands
+    Lint,
+}
+
+#[derive(Parser, Debug)]
+enum ImportSubCommand {
+    /// Import strokes (and commands) from easystroke actions file
+    Easystroke(EasystrokeImportArguments),
+    /// Import gestures from JSON sample set in $1 recognizer format
+    OneDollar(OneDollarImportArguments),
+}
+
+#[derive(Parser, Debug)]
+struct EasystrokeImportArguments {
+    /// easystroke actions file. By default newest ~/.easystroke/actions* used
+    actions: Option<PathBuf>,
+    /// What to do with patterns that already exist
+    #[arg(long = "on-conflict", value_enum, default_value_t = ConflictStrategy::Skip)]
+    on_conflict: ConflictStrategy,
+}
+
+#[derive(Parser, Debug)]
+struct OneDollarImportArguments {
+    /// JSON file with gesture samples
+    samples: PathBuf,
+    /// What to do with patterns that already exist
+    #[arg(long = "on-conflict", value_enum, default_value_t = ConflictStrategy::Skip)]
+    on_conflict: ConflictStrategy,
+}
+
+#[derive(Parser, Debug)]
+struct PackExportArguments {
+    /// Pattern names to export
+    names: Vec<String>,
+    /// Output file. By default pack written to stdout
+    #[arg(long = "output", short = 'o')]
+    output: Option<PathBuf>,
+    /// Include commands bound to exported patterns
+    #[arg(long = "with-commands", default_value_t = false)]
+    with_commands: bool,
+}
+
+#[derive(Parser, Debug)]
+struct PackImportArguments {
+    /// Pack file produced by `wlrune pack export`
+    pack: PathBuf,
+    /// What to do with patterns that already exist
+    #[arg(long = "on-conflict", value_enum, default_value_t = ConflictStrategy::Skip)]
+    on_conflict: ConflictStrategy,
+    /// Print commands from pack for imported patterns
+    #[arg(long = "with-commands", default_value_t = false)]
+    with_commands: bool,
 }
 
 fn main() -> Result<(), ()> {
     let args = AppArguments::parse();
+
+    // broken config should be diagnosed rather than rejected on load
+    if let AppSubCommand::Config(ConfigSubCommand::Check) = args.subcommand {
+        let Some(config_path) = find_config_path(args.config_path.as_deref())? else {
+            eprintln!("config file not found, default configuration used");
+            return Ok(());
+        };
+        return check_config(&config_path);
+    }
+
     let config = load_config(args.config_path.as_deref())?;
 
     match args.subcommand {
         AppSubCommand::Recognize => {
             if config.commands.len() == 0 {
-                let config_path = args.config_path.unwrap_or_else(|| {
-                    default_config_pathes().unwrap()[0].display().to_string()
-                });
+                let config_path = args
+                    .config_path
+                    .unwrap_or_else(|| default_config_pathes().unwrap()[0].display().to_string());
                 eprintln!("command list is empty");
                 eprintln!("record some patterns by using `wlrune record --name up`");
                 eprintln!("and define commands in configuration file {}", &config_path);
@@ -72,102 +192,226 @@ fn main() -> Result<(), ()> {
                 .commands
                 .iter()
                 .map(|cmd| cmd.pattern.as_str())
+                .filter(|name| PointerGesture::from_name(name).is_none())
                 .collect::<HashSet<_>>()
                 .into_iter();
             let patterns = load_gestures(pattern_names).unwrap();
+            let pointer_gestures = config
+                .commands
+                .iter()
+                .filter_map(|cmd| PointerGesture::from_name(&cmd.pattern))
+                .collect::<HashSet<_>>();
+
+            let mut unistroke_recognizer = UnistrokeRecognizer::new(recognizer_conf);
 
-            let Some(gesture_path) = get_user_gesture() else {
+            for unistroke in &patterns {
+                if let Err(err) =
+                    unistroke_recognizer.add_pattern(unistroke.name.clone(), &unistroke.path)
+                {
+                    eprintln!(
+                        "WARNING: sample of pattern {} skipped, reason: {}",
+                        unistroke.name,
+                        err.description()
+                    );
+                }
+            }
+            let unistroke_recognizer = Arc::new(unistroke_recognizer);
+
+            let mut capture = GestureSource::new(&config)?;
+            capture.set_pointer_gestures(pointer_gestures);
+            let visible = config.overlay.mode == OverlayMode::Visible;
+            if visible && config.overlay.live_preview && !unistroke_recognizer.patterns.is_empty() {
+                capture.set_preview(LivePreview {
+                    recognizer: unistroke_recognizer.clone(),
+                    commands: config.commands.clone(),
+                    treshold: recognizer_conf.command_execute_treshold,
+                    min_points: recognizer_conf.point_count_treshold as usize,
+                    interval: Duration::try_from_secs_f64(config.overlay.preview_interval)
+                        .unwrap_or_default(),
+                });
+            }
+            let Some(gesture_path) = capture.next_gesture() else {
+                if let Some(reason) = capture.cancel_reason() {
+                    eprintln!("gesture cancelled, reason: {}", reason.description());
+                    drop(capture);
+                    std::process::exit(reason.exit_code());
+                }
                 return Ok(());
             };
+            let pointer_gesture = capture.pointer_gesture();
+
+            // bindings restricted to another output or region don't take part in recognition
+            let origin = capture.origin().cloned();
+            let bindings = bindings_for(origin.as_ref(), &config.commands);
+            let recognizer = unistroke_recognizer
+                .restricted(|name| bindings.iter().any(|cmd| cmd.pattern == name));
+
+            let toast_duration = Duration::try_from_secs_f64(config.overlay.toast_duration)
+                .ok()
+                .filter(|_| visible)
+                .unwrap_or_default();
+            let mut show_result = |text: &str| {
+                if let Some(end) = gesture_path.last().filter(|_| !toast_duration.is_zero()) {
+                    capture.show_toast(text, *end);
+                }
+            };
 
-            if gesture_path.len() < config.recognizer.point_count_treshold as usize {
+            let too_short = gesture_path.len() < config.recognizer.point_count_treshold as usize;
+            if pointer_gesture.is_none() && too_short {
                 eprintln!("skip gesture saving, reason: pattern point count less than specified in config ({})", config.recognizer.point_count_treshold);
+                notify(
+                    &config.notifications,
+                    "Gesture rejected",
+                    &format!(
+                        "too few points: {} of {} required",
+                        gesture_path.len(),
+                        config.recognizer.point_count_treshold
+                    ),
+                    Urgency::Normal,
+                );
+                show_result("unrecognized");
+                capture.close_after(toast_duration).join().unwrap();
                 return Err(());
             }
 
-            let mut unistroke_recognizer = UnistrokeRecognizer {
-                angle_range_rad: degrees_to_radians(recognizer_conf.rotation_angle_range),
-                angle_precision: degrees_to_radians(recognizer_conf.rotation_angle_treshold),
-                width: recognizer_conf.width,
-                height: recognizer_conf.height,
-                resample_num_points: recognizer_conf.resample_num_points,
-                patterns: Vec::new(),
-            };
-
-            for unistroke in &patterns {
-                unistroke_recognizer.add_pattern(unistroke.name.clone(), &unistroke.path);
+            if pointer_gesture.is_none() && recognizer.patterns.is_empty() {
+                eprintln!(
+                    "skip command execution, reason: no drawn patterns bound where gesture started"
+                );
+                show_result("unrecognized");
+                capture.close_after(toast_duration).join().unwrap();
+                return Err(());
             }
 
-            let (unistroke, similarity) = unistroke_recognizer.recognize_unistroke(&gesture_path);
-            eprintln!(
-                "recognized as {} (similarity ≈ {:.02})",
-                unistroke.name, similarity
-            );
-
-            if similarity >= recognizer_conf.command_execute_treshold {
-                let raw_command = &config
-                    .commands
-                    .iter()
-                    .find(|cmd| cmd.pattern == unistroke.name)
-                    .unwrap()
-                    .command;
-
-                let mut cmd = Command::new("bash");
-
-                cmd.stderr(Stdio::null());
-                cmd.stdout(Stdio::null());
-                cmd.stdin(Stdio::null());
-
-                cmd.arg("-c");
-                cmd.arg(raw_command);
+            // wheel and rocker gestures are exact, unlike drawn ones
+            let recognition = match pointer_gesture {
+                Some(pointer_gesture) => Ok((pointer_gesture.name(), 1.0)),
+                None => recognizer
+                    .recognize_unistroke(&gesture_path)
+                    .map(|(unistroke, similarity)| (unistroke.name.as_str(), similarity)),
+            };
+            let (name, similarity) = match recognition {
+                Ok(recognition) => recognition,
+                Err(err) => {
+                    eprintln!("skip command execution, reason: {}", err.description());
+                    notify(
+                        &config.notifications,
+                        "Gesture rejected",
+                        err.description(),
+                        Urgency::Normal,
+                    );
+                    show_result("unrecognized");
+                    capture.close_after(toast_duration).join().unwrap();
+                    return Err(());
+                }
+            };
+            eprintln!("recognized as {} (similarity ≈ {:.02})", name, similarity);
 
-                let _child = cmd.spawn().unwrap();
+            let gesture_command = bindings.iter().find(|cmd| cmd.pattern == name);
+            let recognized =
+                similarity >= recognizer_conf.command_execute_treshold && gesture_command.is_some();
+            if recognized {
+                show_result(&format!("{} {:.02}", name, similarity));
+            } else {
+                show_result("unrecognized");
+            }
+            // overlay stays visible in background, while command is starting
+            let overlay = capture.close_after(toast_duration);
+
+            let result = if let Some(gesture_command) = gesture_command.filter(|_| recognized) {
+                if config.notifications.on_success {
+                    notify(
+                        &config.notifications,
+                        &format!("Gesture {}", name),
+                        &format!("similarity ≈ {:.02}", similarity),
+                        Urgency::Low,
+                    );
+                }
+
+                run_command(&config, gesture_command)
+            } else if gesture_command.is_none() {
+                eprintln!(
+                    "skip command execution, reason: {} is not bound where gesture started",
+                    name
+                );
+                notify(
+                    &config.notifications,
+                    "Gesture rejected",
+                    &format!("{} is not bound where gesture started", name),
+                    Urgency::Normal,
+                );
+                Ok(())
             } else {
                 eprintln!(
                     "skip command execution, reason: similarity less than specified in config ({})",
                     recognizer_conf.command_execute_treshold
                 );
-            }
-        }
-        AppSubCommand::Record(args) => {
-            let gesture_file_path = gesture_file_path(&args.name)?;
-
-            if gesture_file_path.exists() && !args.force {
-                eprintln!("ERROR: pattern with name {} already exist, use --force flag if you want override it", &args.name);
-                return Err(());
-            }
-
-            let Some(gesture_path) = get_user_gesture() else {
-                return Ok(());
+                notify(
+                    &config.notifications,
+                    "Gesture rejected",
+                    &format!(
+                        "closest pattern {} (similarity ≈ {:.02}, required {})",
+                        name, similarity, recognizer_conf.command_execute_treshold
+                    ),
+                    Urgency::Normal,
+                );
+                Ok(())
             };
 
-            if gesture_path.len() < config.recognizer.point_count_treshold as usize {
-                eprintln!("skip gesture saving, reason: pattern point count less than specified in config ({})", config.recognizer.point_count_treshold);
-                return Err(());
-            }
-
-            save_gesture(&gesture_file_path, &gesture_path).unwrap();
+            overlay.join().unwrap();
+            result?;
         }
+        AppSubCommand::Record(args) => {
+            record_pattern(&config, &args.name, args.samples as usize, args.force)?;
+        }
+        AppSubCommand::Cheatsheet => {
+            show_cheatsheet(&config)?;
+        }
+        AppSubCommand::Pack(PackSubCommand::Export(args)) => {
+            export_pack(
+                &config,
+                &args.names,
+                args.with_commands,
+                args.output.as_deref(),
+            )?;
+        }
+        AppSubCommand::Pack(PackSubCommand::Import(args)) => {
+            import_pack(&args.pack, args.on_conflict, args.with_commands)?;
+        }
+        AppSubCommand::Import(ImportSubCommand::Easystroke(args)) => {
+            import_easystroke(args.actions.as_deref(), args.on_conflict)?;
+        }
+        AppSubCommand::Import(ImportSubCommand::OneDollar(args)) => {
+            import_dollar_one(&args.samples, args.on_conflict)?;
+        }
+        AppSubCommand::Pattern(PatternSubCommand::Lint) => {
+            lint_patterns(&config)?;
+        }
+        AppSubCommand::Config(ConfigSubCommand::Check) => unreachable!(),
     }
 
     Ok(())
 }
 
-fn load_config(config_path: Option<&str>) -> Result<AppConfig, ()> 
-{
+fn load_config(config_path: Option<&str>) -> Result<AppConfig, ()> {
+    match find_config_path(config_path)? {
+        Some(config_path) => AppConfig::load(&config_path),
+        None => Ok(AppConfig::default()),
+    }
+}
+
+fn find_config_path(config_path: Option<&str>) -> Result<Option<PathBuf>, ()> {
     if let Some(config_path) = config_path {
-        let config_path = Path::new(&config_path);
-        return Ok(AppConfig::load(&config_path)?);
+        return Ok(Some(PathBuf::from(config_path)));
     }
 
     for config_path in default_config_pathes()? {
         if config_path.exists() {
-            let config = AppConfig::load(&config_path)?;
-            return Ok(config)
+            return Ok(Some(config_path));
         }
     }
 
-    Ok(AppConfig::default())
+    Ok(None)
 }
 
 fn default_config_pathes() -> Result<Vec<PathBuf>, ()> {
@@ -179,99 +423,8 @@ fn default_config_pathes() -> Result<Vec<PathBuf>, ()> {
     let xdg_config_home = Path::new(&xdg_config_home);
 
     Ok(vec![
-      xdg_config_home.join("wlrune/config.yaml"),
-      xdg_config_home.join("wlrune/config.yml"),
-      xdg_config_home.join("wlrune/config"),
+        xdg_config_home.join("wlrune/config.yaml"),
+        xdg_config_home.join("wlrune/config.yml"),
+        xdg_config_home.join("wlrune/config"),
     ])
 }
-
-fn load_gestures<'a, I>(names: I) -> Result<Vec<Unistroke>, ()>
-where
-    I: Iterator<Item = &'a str>,
-{
-    let mut patterns = Vec::new();
-    for name in names {
-        let path = gesture_file_path(name)?;
-        if path.is_file() || path.is_symlink() {
-            let gesture_file = File::open(&path).map_err(|err| {
-                eprintln!(
-                    "ERROR: couldn't read gesture file {}, {}",
-                    &path.display(),
-                    err
-                );
-            })?;
-
-            let mut pattern_path = Vec::new();
-            let reader = BufReader::new(gesture_file);
-            for line in reader.lines() {
-                let line = line.map_err(|err| {
-                    eprintln!("ERROR: couldn't read line {err}");
-                })?;
-
-                let items: Vec<&str> = line.split(" ").collect();
-                let x: f64 = items[0].parse().unwrap();
-                let y: f64 = items[1].parse().unwrap();
-
-                pattern_path.push(Point::new(x, y));
-            }
-
-            patterns.push(Unistroke {
-                name: name.to_string(),
-                path: pattern_path,
-            });
-        }
-    }
-
-    Ok(patterns)
-}
-
-fn save_gesture(gesture_file_path: &Path, path: &[Point]) -> Result<(), ()> {
-    let pattern_directory = gesture_file_path.parent().unwrap();
-    create_dir_all(&pattern_directory).map_err(|err| {
-        eprintln!(
-            "ERROR: couldn't create {}: {}",
-            pattern_directory.display(),
-            err
-        );
-    })?;
-
-    let mut gesture_file = File::create(&gesture_file_path).map_err(|err| {
-        eprintln!("ERROR: couldn't open {}: {}", gesture_file_path.display(), err);
-    })?;
-
-    let serialized_path = path
-        .iter()
-        .map(|p| format!("{} {}", p.x, p.y))
-        .collect::<Vec<_>>()
-        .join("\n");
-
-    gesture_file
-        .write_all(serialized_path.as_bytes())
-        .map_err(|err| {
-            eprintln!("ERROR: cloudn't write gesture to file: {err}");
-        })?;
-
-    gesture_file.sync_all().map_err(|err| {
-        eprintln!("ERROR: couldn't sync changes with file: {err}");
-    })?;
-
-    Ok(())
-}
-
-fn gesture_file_path(name: &str) -> Result<PathBuf, ()> {
-    let gesture_data_dir = gesture_data_dir()?;
-    let gesture_path = gesture_data_dir.join("patterns").join(name);
-
-    Ok(gesture_path)
-}
-
-fn gesture_data_dir() -> Result<PathBuf, ()> {
-    let home = env::var("HOME").map_err(|err| {
-        eprintln!("ERROR: coludn't find $HOME: {err}");
-    })?;
-    let xdg_home = env::var("XDG_DATA_HOME").unwrap_or(format!("{home}/.local/share"));
-    let xdg_home = Path::new(&xdg_home);
-    let gesture_data_dir = xdg_home.join("wlrune");
-
-    Ok(gesture_data_dir)
-}
@@ -0,0 +1,65 @@
+use crate::{
+    config::NotificationConfig,
+    dbus::{BusConnection, MessageWriter},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Urgency {
+    Low = 0,
+    Normal = 1,
+    Critical = 2,
+}
+
+/// Show desktop notification if enabled in config, failures only reported to stderr
+pub fn notify(config: &NotificationConfig, summary: &str, body: &str, urgency: Urgency) {
+    if !config.enabled {
+        return;
+    }
+
+    let result = BusConnection::session()
+        .and_then(|mut bus| send_notification(&mut bus, config, summary, body, urgency));
+
+    if let Err(err) = result {
+        eprintln!("WARNING: failed to send notification: {err}");
+    }
+}
+
+/// Call `org.freedesktop.Notifications.Notify`, returns notification id
+pub fn send_notification(
+    bus: &mut BusConnection,
+    config: &NotificationConfig,
+    summary: &str,
+    body: &str,
+    urgency: Urgency,
+) -> std::io::Result<u32> {
+    let mut message = MessageWriter::new();
+    message.string("wlrune"); // app_name
+    message.u32(0); // replaces_id
+    message.string(&config.icon); // app_icon
+    message.string(summary);
+    message.string(body);
+    message.array(4, |_| {}); // actions
+    message.array(8, |message| {
+        message.align(8);
+        message.string("urgency");
+        message.signature("y");
+        message.u8(urgency as u8);
+    }); // hints
+    message.i32(config.expire_timeout); // expire_timeout
+
+    let reply = bus.call(
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+        "Notify",
+        "susssasa{sv}i",
+        &message.buf,
+    )?;
+
+    let id = reply
+        .get(..4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .unwrap_or_default();
+
+    Ok(id)
+}
@@ -0,0 +1,249 @@
+use std::{
+    collections::HashSet,
+    fs::{read_to_string, File},
+    io::{self, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{print_commands, AppConfig, GestureCommand},
+    patterns::{
+        gesture_file_path, load_gesture, pattern_names, serialize_gesture, store_gesture,
+        validate_pattern_name, ConflictStrategy,
+    },
+    recognizer::Point,
+};
+
+/// Version 2 stores several samples per pattern, version 1 packs (single path) still accepted
+const PACK_VERSION: u32 = 2;
+
+/// Single YAML document with patterns and (optionally) their commands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GesturePack {
+    /// Pack format version
+    pub version: u32,
+    pub patterns: Vec<PackedPattern>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<GestureCommand>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackedPattern {
+    pub name: String,
+    /// FNV-1a 64 hash of samples serialized in gesture file format
+    pub checksum: String,
+    #[serde(default)]
+    pub samples: Vec<Vec<[f64; 2]>>,
+    /// The only sample of pattern in version 1 packs
+    #[serde(default, skip_serializing)]
+    pub path: Vec<[f64; 2]>,
+}
+
+pub fn export_pack(
+    config: &AppConfig,
+    names: &[String],
+    with_commands: bool,
+    output: Option<&Path>,
+) -> Result<(), ()> {
+    let names = if names.is_empty() {
+        pattern_names()?
+    } else {
+        names.to_vec()
+    };
+
+    let mut patterns = Vec::new();
+    for name in &names {
+        let gesture_file_path = gesture_file_path(name)?;
+        if validate_pattern_name(name).is_err() || !gesture_file_path.is_file() {
+            eprintln!("ERROR: pattern with name {} not found", name);
+            return Err(());
+        }
+
+        let samples = match load_gesture(&gesture_file_path) {
+            Ok(samples) => samples,
+            Err(reason) => {
+                eprintln!("WARNING: pattern {} skipped, {}", name, reason);
+                continue;
+            }
+        };
+        patterns.push(PackedPattern {
+            name: name.clone(),
+            checksum: pattern_checksum(&samples),
+            samples: samples
+                .iter()
+                .map(|path| path.iter().map(|p| [p.x, p.y]).collect())
+                .collect(),
+            path: Vec::new(),
+        });
+    }
+
+    let commands = if with_commands {
+        config
+            .commands
+            .iter()
+            .filter(|cmd| patterns.iter().any(|pattern| pattern.name == cmd.pattern))
+            .cloned()
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let pack = GesturePack {
+        version: PACK_VERSION,
+        patterns,
+        commands,
+    };
+
+    let raw = serde_yml::to_string(&pack).map_err(|err| {
+        eprintln!("ERROR: failed to serialize pack: {err}");
+    })?;
+
+    match output {
+        Some(output) => {
+            let mut file = File::create(output).map_err(|err| {
+                eprintln!("ERROR: couldn't open {}: {}", output.display(), err);
+            })?;
+            file.write_all(raw.as_bytes()).map_err(|err| {
+                eprintln!(
+                    "ERROR: couldn't write pack to {}: {}",
+                    output.display(),
+                    err
+                );
+            })?;
+        }
+        None => {
+            io::stdout().write_all(raw.as_bytes()).map_err(|err| {
+                eprintln!("ERROR: couldn't write pack to stdout: {err}");
+            })?;
+        }
+    }
+
+    eprintln!("exported {} pattern(s)", pack.patterns.len());
+
+    Ok(())
+}
+
+pub fn import_pack(
+    pack_path: &Path,
+    on_conflict: ConflictStrategy,
+    with_commands: bool,
+) -> Result<(), ()> {
+    let raw = read_to_string(pack_path).map_err(|err| {
+        eprintln!(
+            "ERROR: failed to read pack {}, {}",
+            pack_path.display(),
+            err
+        );
+    })?;
+
+    let mut pack: GesturePack = serde_yml::from_str(&raw).map_err(|err| {
+        eprintln!(
+            "ERROR: failed to parse pack {}, {}",
+            pack_path.display(),
+            err
+        );
+    })?;
+
+    match pack.version {
+        1 => {
+            for pattern in &mut pack.patterns {
+                pattern.samples = vec![std::mem::take(&mut pattern.path)];
+            }
+        }
+        PACK_VERSION => {}
+        _ => {
+            eprintln!(
+                "ERROR: unsupported pack version {} (expected {} or older)",
+                pack.version, PACK_VERSION
+            );
+            return Err(());
+        }
+    }
+
+    // verify whole pack before touching pattern directory
+    let mut seen = HashSet::new();
+    for pattern in &pack.patterns {
+        if let Err(reason) = validate_pattern_name(&pattern.name) {
+            eprintln!(
+                "ERROR: invalid pattern name {:?} in pack, {}",
+                pattern.name, reason
+            );
+            return Err(());
+        }
+
+        if !seen.insert(pattern.name.as_str()) {
+            eprintln!("ERROR: pattern {} defined twice in pack", pattern.name);
+            return Err(());
+        }
+
+        let checksum = pattern_checksum(&unpack_samples(&pattern.samples));
+        if checksum != pattern.checksum {
+            eprintln!(
+                "ERROR: checksum mismatch for pattern {} (expected {}, got {})",
+                pattern.name, pattern.checksum, checksum
+            );
+            return Err(());
+        }
+    }
+
+    let mut imported_names = Vec::new();
+    for pattern in &pack.patterns {
+        let samples = unpack_samples(&pattern.samples);
+        let target = gesture_file_path(&pattern.name)?;
+
+        let up_to_date = load_gesture(&target)
+            .is_ok_and(|existing| pattern_checksum(&existing) == pattern.checksum);
+        if target.exists() && up_to_date {
+            eprintln!("pattern {} already up to date", pattern.name);
+            imported_names.push((pattern.name.as_str(), pattern.name.clone()));
+            continue;
+        }
+
+        if let Some(name) = store_gesture(&pattern.name, &samples, on_conflict)? {
+            imported_names.push((pattern.name.as_str(), name));
+        }
+    }
+
+    eprintln!("imported {} pattern(s)", imported_names.len());
+
+    if with_commands && !pack.commands.is_empty() {
+        let commands = pack
+            .commands
+            .iter()
+            .filter_map(|cmd| {
+                let (_, name) = imported_names
+                    .iter()
+                    .find(|(original, _)| *original == cmd.pattern)?;
+                Some(GestureCommand {
+                    pattern: name.clone(),
+                    ..cmd.clone()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        print_commands(&commands)?;
+    }
+
+    Ok(())
+}
+
+fn unpack_samples(samples: &[Vec<[f64; 2]>]) -> Vec<Vec<Point>> {
+    samples
+        .iter()
+        .map(|path| path.iter().map(|[x, y]| Point::new(*x, *y)).collect())
+        .collect()
+}
+
+/// Checksum of samples serialized the way `save_gesture` writes them, so it doesn't
+/// depend on formatting of the file pattern was loaded from
+fn pattern_checksum(samples: &[Vec<Point>]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in serialize_gesture(samples).bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    format!("{:016x}", hash)
+}
@@ -0,0 +1,380 @@
+use std::{
+    env,
+    fs::{create_dir_all, read_dir, File},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{ButtonName, PointerButton},
+    recognizer::{Point, Unistroke},
+};
+
+/// Wheel and button chord (rocker) gestures, bound by reserved pattern names
+/// and captured without drawn path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PointerGesture {
+    WheelUp,
+    WheelDown,
+    WheelLeft,
+    WheelRight,
+    /// Left button clicked while another one is held
+    RockerLeft,
+    /// Right button clicked while another one is held
+    RockerRight,
+    /// Middle button clicked while another one is held
+    RockerMiddle,
+}
+
+impl PointerGesture {
+    pub const ALL: [PointerGesture; 7] = [
+        PointerGesture::WheelUp,
+        PointerGesture::WheelDown,
+        PointerGesture::WheelLeft,
+        PointerGesture::WheelRight,
+        PointerGesture::RockerLeft,
+        PointerGesture::RockerRight,
+        PointerGesture::RockerMiddle,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PointerGesture::WheelUp => "wheel_up",
+            PointerGesture::WheelDown => "wheel_down",
+            PointerGesture::WheelLeft => "wheel_left",
+            PointerGesture::WheelRight => "wheel_right",
+            PointerGesture::RockerLeft => "rocker_left",
+            PointerGesture::RockerRight => "rocker_right",
+            PointerGesture::RockerMiddle => "rocker_middle",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|gesture| gesture.name() == name)
+    }
+
+    /// Rocker gesture made by click of `button` (evdev code) while another one is held
+    pub fn rocker(button: u32) -> Option<Self> {
+        let rockers = [
+            (ButtonName::Left, PointerGesture::RockerLeft),
+            (ButtonName::Right, PointerGesture::RockerRight),
+            (ButtonName::Middle, PointerGesture::RockerMiddle),
+        ];
+
+        rockers
+            .into_iter()
+            .find(|(name, _)| PointerButton::Named(*name).code() == button)
+            .map(|(_, gesture)| gesture)
+    }
+}
+
+/// What to do when pattern name already taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConflictStrategy {
+    /// Keep existing pattern
+    Skip,
+    /// Replace existing pattern with new one
+    Overwrite,
+    /// Save new pattern under free name (name-2, name-3, ...)
+    Rename,
+}
+
+/// Check that name can be used as gesture file inside of patterns directory
+pub fn validate_pattern_name(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() {
+        return Err("name is empty");
+    }
+    if name.starts_with('.') {
+        return Err("name can't start with '.'");
+    }
+    if name.contains('/') {
+        return Err("name can't contain '/'");
+    }
+    if name.contains('\0') {
+        return Err("name can't contain NUL");
+    }
+
+    Ok(())
+}
+
+/// Load samples of named patterns, files that can't be read are skipped with warning
+pub fn load_gestures<'a, I>(names: I) -> Result<Vec<Unistroke>, ()>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let mut patterns = Vec::new();
+    for name in names {
+        let path = gesture_file_path(name)?;
+        if !path.is_file() && !path.is_symlink() {
+            continue;
+        }
+
+        let samples = match load_gesture(&path) {
+            Ok(samples) => samples,
+            Err(reason) => {
+                eprintln!("WARNING: pattern {} skipped, {}", name, reason);
+                continue;
+            }
+        };
+
+        for sample in samples {
+            patterns.push(Unistroke {
+                name: name.to_string(),
+                path: sample,
+            });
+        }
+    }
+
+    Ok(patterns)
+}
+
+/// Load all samples of pattern, samples in gesture file separated by empty line.
+/// Error tells which file and line couldn't be read
+pub fn load_gesture(gesture_file_path: &Path) -> Result<Vec<Vec<Point>>, String> {
+    let gesture_file = File::open(gesture_file_path).map_err(|err| {
+        format!(
+            "couldn't read gesture file {}, {}",
+            gesture_file_path.display(),
+            err
+        )
+    })?;
+
+    let mut samples = Vec::new();
+    let mut pattern_path = Vec::new();
+    let reader = BufReader::new(gesture_file);
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| {
+            format!(
+                "couldn't read gesture file {}, line {}: {}",
+                gesture_file_path.display(),
+                index + 1,
+                err
+            )
+        })?;
+
+        if line.trim().is_empty() {
+            if !pattern_path.is_empty() {
+                samples.push(std::mem::take(&mut pattern_path));
+            }
+            continue;
+        }
+
+        let point = parse_point(&line).ok_or_else(|| {
+            format!(
+                "invalid gesture file {}, line {}: expected `x y` pair, got {:?}",
+                gesture_file_path.display(),
+                index + 1,
+                line
+            )
+        })?;
+
+        pattern_path.push(point);
+    }
+
+    if !pattern_path.is_empty() {
+        samples.push(pattern_path);
+    }
+
+    Ok(samples)
+}
+
+fn parse_point(line: &str) -> Option<Point> {
+    let mut items = line.split_whitespace();
+    let x: f64 = items.next()?.parse().ok()?;
+    let y: f64 = items.next()?.parse().ok()?;
+    if items.next().is_some() {
+        return None;
+    }
+
+    Some(Point::new(x, y))
+}
+
+pub fn save_gesture(gesture_file_path: &Path, samples: &[Vec<Point>]) -> Result<(), ()> {
+    let pattern_directory = gesture_file_path.parent().unwrap();
+    create_dir_all(pattern_directory).map_err(|err| {
+        eprintln!(
+            "ERROR: couldn't create {}: {}",
+            pattern_directory.display(),
+            err
+        );
+    })?;
+
+    let mut gesture_file = File::create(gesture_file_path).map_err(|err| {
+        eprintln!(
+            "ERROR: couldn't open {}: {}",
+            gesture_file_path.display(),
+            err
+        );
+    })?;
+
+    gesture_file
+        .write_all(serialize_gesture(samples).as_bytes())
+        .map_err(|err| {
+            eprintln!("ERROR: cloudn't write gesture to file: {err}");
+        })?;
+
+    gesture_file.sync_all().map_err(|err| {
+        eprintln!("ERROR: couldn't sync changes with file: {err}");
+    })?;
+
+    Ok(())
+}
+
+/// Save pattern respecting conflict strategy, returns name under which pattern was saved
+pub fn store_gesture(
+    name: &str,
+    samples: &[Vec<Point>],
+    on_conflict: ConflictStrategy,
+) -> Result<Option<String>, ()> {
+    let mut stored_name = name.to_string();
+    let mut target = gesture_file_path(name)?;
+
+    if target.exists() {
+        match on_conflict {
+            ConflictStrategy::Skip => {
+                eprintln!("skip pattern {}, reason: pattern already exist", name);
+                return Ok(None);
+            }
+            ConflictStrategy::Overwrite => {
+                eprintln!("overwrite pattern {}", name);
+            }
+            ConflictStrategy::Rename => {
+                let mut index = 2;
+                while target.exists() {
+                    stored_name = format!("{}-{}", name, index);
+                    target = gesture_file_path(&stored_name)?;
+                    index += 1;
+                }
+                eprintln!("save pattern {} as {}", name, stored_name);
+            }
+        }
+    }
+
+    save_gesture(&target, samples)?;
+
+    Ok(Some(stored_name))
+}
+
+/// Gesture file content, one `x y` pair per line and empty line between samples
+pub fn serialize_gesture(samples: &[Vec<Point>]) -> String {
+    samples
+        .iter()
+        .map(|path| {
+            path.iter()
+                .map(|p| format!("{} {}", p.x, p.y))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Names of all recorded patterns, sorted alphabetically
+pub fn pattern_names() -> Result<Vec<String>, ()> {
+    let patterns_dir = gesture_data_dir()?.join("patterns");
+    if !patterns_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let entries = read_dir(&patterns_dir).map_err(|err| {
+        eprintln!("ERROR: couldn't read {}: {}", patterns_dir.display(), err);
+    })?;
+
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|err| {
+            eprintln!("ERROR: couldn't read {}: {}", patterns_dir.display(), err);
+        })?;
+
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+
+        // hidden files (editor backups and so on) can't be patterns
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            if validate_pattern_name(name).is_ok() {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names.sort();
+    Ok(names)
+}
+
+pub fn gesture_file_path(name: &str) -> Result<PathBuf, ()> {
+    let gesture_data_dir = gesture_data_dir()?;
+    let gesture_path = gesture_data_dir.join("patterns").join(name);
+
+    Ok(gesture_path)
+}
+
+fn gesture_data_dir() -> Result<PathBuf, ()> {
+    let home = env::var("HOME").map_err(|err| {
+        eprintln!("ERROR: coludn't find $HOME: {err}");
+    })?;
+    let xdg_home = env::var("XDG_DATA_HOME").unwrap_or(format!("{home}/.local/share"));
+    let xdg_home = Path::new(&xdg_home);
+    let gesture_data_dir = xdg_home.join("wlrune");
+
+    Ok(gesture_data_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("wlrune-patterns-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn samples_survive_save_and_load() {
+        let samples = vec![
+            vec![Point::new(0.5, 1.0), Point::new(-2.25, 3.0)],
+            vec![Point::new(10.0, 20.0)],
+            vec![
+                Point::new(1e-3, 1e6),
+                Point::new(7.0, 8.0),
+                Point::new(9.0, 0.0),
+            ],
+        ];
+        let path = temp_file("round-trip");
+        save_gesture(&path, &samples).unwrap();
+        let loaded = load_gesture(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, Ok(samples));
+    }
+
+    #[test]
+    fn extra_empty_lines_dont_make_empty_samples() {
+        let path = temp_file("empty-lines");
+        fs::write(&path, "\n1 2\n3 4\n\n\n  \n5 6\n\n").unwrap();
+        let loaded = load_gesture(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            loaded,
+            Ok(vec![
+                vec![Point::new(1.0, 2.0), Point::new(3.0, 4.0)],
+                vec![Point::new(5.0, 6.0)]
+            ])
+        );
+    }
+
+    #[test]
+    fn malformed_line_is_reported_with_its_number() {
+        let path = temp_file("malformed");
+        fs::write(&path, "1 2\n3 4 5\n").unwrap();
+        let loaded = load_gesture(&path);
+        fs::remove_file(&path).unwrap();
+
+        let reason = loaded.unwrap_err();
+        assert!(reason.contains("line 2"), "{reason}");
+        assert!(reason.contains(&path.display().to_string()), "{reason}");
+    }
+}
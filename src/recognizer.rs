@@ -1,13 +1,36 @@
 use cgmath::{MetricSpace, Vector2};
-use std::f64::{consts::PI, INFINITY};
+use std::f64::consts::PI;
+
+use crate::config::RecognizerConfig;
 
 pub type Point = Vector2<f64>;
 
+#[derive(Clone)]
 pub struct Unistroke {
     pub name: String,
     pub path: Vec<Point>,
 }
 
+/// Why stroke can't be recognized or used as pattern
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StrokeError {
+    Empty,
+    /// All points are at the same position, e.g. click without motion
+    ZeroLength,
+    /// Infinite or NaN coordinates
+    InvalidPoint,
+}
+
+impl StrokeError {
+    pub fn description(self) -> &'static str {
+        match self {
+            StrokeError::Empty => "stroke has no points",
+            StrokeError::ZeroLength => "stroke has zero length, pointer didn't move",
+            StrokeError::InvalidPoint => "stroke has non-finite coordinates",
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub struct Rect {
     x: f64,
@@ -26,45 +49,98 @@ pub struct UnistrokeRecognizer {
 }
 
 impl UnistrokeRecognizer {
-    pub fn recognize_unistroke(&self, path: &[Point]) -> (&Unistroke, f64) {
-        let diagonal: f64 = Point::new(self.width, self.height).distance(Point::new(0.0, 0.0));
-        let path = self.normalize_stroke_path(path);
+    pub fn new(config: &RecognizerConfig) -> Self {
+        UnistrokeRecognizer {
+            angle_range_rad: degrees_to_radians(config.rotation_angle_range),
+            angle_precision: degrees_to_radians(config.rotation_angle_treshold),
+            width: config.width,
+            height: config.height,
+            resample_num_points: config.resample_num_points,
+            patterns: Vec::new(),
+        }
+    }
+
+    pub fn recognize_unistroke(&self, path: &[Point]) -> Result<(&Unistroke, f64), StrokeError> {
+        let path = self.normalize_stroke_path(path)?;
 
         let mut similar_pattern = &self.patterns[0];
-        let mut b = f64::INFINITY;
+        let mut best_similarity = -f64::INFINITY;
         for pattern in &self.patterns {
-            let d = distance_at_best_angle(
-                &path,
-                &pattern.path,
-                -self.angle_range_rad,
-                self.angle_range_rad,
-                self.angle_precision,
-            );
+            let similarity = self.similarity(&path, &pattern.path);
 
-            if d < b {
-                b = d;
-                similar_pattern = &pattern;
+            if similarity > best_similarity {
+                best_similarity = similarity;
+                similar_pattern = pattern;
             }
         }
 
-        (similar_pattern, 1.0 - b / (diagonal / 2.0))
+        Ok((similar_pattern, best_similarity))
     }
 
-    pub fn add_pattern(&mut self, name: String, path: &[Point]) {
-        let path = self.normalize_stroke_path(path);
+    /// Similarity between two normalized paths (1.0 for identical paths)
+    pub fn similarity(&self, path: &[Point], pattern_path: &[Point]) -> f64 {
+        let diagonal: f64 = Point::new(self.width, self.height).distance(Point::new(0.0, 0.0));
+        let d = distance_at_best_angle(
+            path,
+            pattern_path,
+            -self.angle_range_rad,
+            self.angle_range_rad,
+            self.angle_precision,
+        );
+
+        1.0 - d / (diagonal / 2.0)
+    }
+
+    /// Recognizer that knows only patterns accepted by `is_bound`, samples aren't normalized again
+    pub fn restricted(&self, is_bound: impl Fn(&str) -> bool) -> UnistrokeRecognizer {
+        UnistrokeRecognizer {
+            angle_range_rad: self.angle_range_rad,
+            angle_precision: self.angle_precision,
+            width: self.width,
+            height: self.height,
+            resample_num_points: self.resample_num_points,
+            patterns: self
+                .patterns
+                .iter()
+                .filter(|unistroke| is_bound(&unistroke.name))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn add_pattern(&mut self, name: String, path: &[Point]) -> Result<(), StrokeError> {
+        let path = self.normalize_stroke_path(path)?;
         let unistroke = Unistroke {
             name,
             path: path.to_vec(),
         };
         self.patterns.push(unistroke);
+
+        Ok(())
     }
 
-    fn normalize_stroke_path(&self, path: &[Point]) -> Vec<Point> {
+    /// Resample, scale and move path to the origin, degenerate strokes are rejected
+    /// as nothing can be recognized in them
+    pub fn normalize_stroke_path(&self, path: &[Point]) -> Result<Vec<Point>, StrokeError> {
+        if path.is_empty() {
+            return Err(StrokeError::Empty);
+        }
+        if path.iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+            return Err(StrokeError::InvalidPoint);
+        }
+        let length = path_length(path);
+        if !length.is_finite() {
+            return Err(StrokeError::InvalidPoint);
+        }
+        if length == 0.0 {
+            return Err(StrokeError::ZeroLength);
+        }
+
         let path = resample(&path, self.resample_num_points);
         let path = scale_to(&path, Point::new(self.width, self.height));
         let path = translate_to(&path, Point::new(0.0, 0.0));
 
-        path
+        Ok(path)
     }
 }
 
@@ -73,46 +149,46 @@ pub fn degrees_to_radians(degrees: f64) -> f64 {
 }
 
 fn resample(path: &[Point], n: u32) -> Vec<Point> {
-    if path.is_empty() {
+    let n = n as usize;
+    let (Some(first), Some(last)) = (path.first(), path.last()) else {
         return Vec::new();
+    };
+
+    let interval = path_length(path) / n.saturating_sub(1) as f64;
+    // path without length (or single requested point) can't be split into intervals
+    if !(interval.is_finite() && interval > 0.0) {
+        return vec![*first; n];
     }
 
-    let path_length = path_length(path) / (n - 1) as f64;
     let mut distance_acc: f64 = 0.0;
 
-    let mut new_path: Vec<Point> = vec![path[0]];
+    let mut new_path: Vec<Point> = vec![*first];
+    let mut prev_point = *first;
 
-    for i in 1..path.len() {
-        let prev_point = path[i - 1];
-        let curr_point = path[i];
+    for &curr_point in &path[1..] {
+        let mut d = curr_point.distance(prev_point);
 
-        let d = curr_point.distance(prev_point);
+        // points are placed exactly one interval apart along the path, several of
+        // them may fall on a long segment
+        while new_path.len() < n && distance_acc + d >= interval {
+            let ratio = (interval - distance_acc) / d;
+            let q_point = Point::new(
+                prev_point.x + ratio * (curr_point.x - prev_point.x),
+                prev_point.y + ratio * (curr_point.y - prev_point.y),
+            );
+            new_path.push(q_point);
 
-        distance_acc += d;
-        let mid_point_count = (distance_acc / path_length) as i32;
-        if mid_point_count > 0 {
-            for mid_point in 0..mid_point_count {
-                let mid_ratio = mid_point as f64 / mid_point_count as f64;
-                let q_point = Point::new(
-                    prev_point.x + mid_ratio * (curr_point.x - prev_point.x),
-                    prev_point.y + mid_ratio * (curr_point.y - prev_point.y),
-                );
-                new_path.push(q_point);
-            }
-            distance_acc -= mid_point_count as f64 * path_length;
+            prev_point = q_point;
+            d = curr_point.distance(prev_point);
+            distance_acc = 0.0;
         }
-    }
 
-    if new_path.len() == (n - 1) as usize {
-        // fix rounding-error
-        new_path.push(*path.last().unwrap());
+        distance_acc += d;
+        prev_point = curr_point;
     }
 
-    assert_eq!(
-        new_path.len(),
-        n as usize,
-        "path length after resampling not equal expected"
-    );
+    // fix rounding-error, accumulated distance may fall short of the last interval
+    new_path.resize(n, *last);
 
     new_path
 }
@@ -132,7 +208,7 @@ fn rotate_by(path: &[Point], theta: f64) -> Vec<Point> {
     let mut new_path = Vec::new();
     for p in path {
         let q_x = (p.x - c.x) * theta.cos() - (p.y - c.y) * theta.sin() + c.x;
-        let q_y = (p.x - c.x) * theta.sin() + (p.y - c.y) * theta.cos() + c.x;
+        let q_y = (p.x - c.x) * theta.sin() + (p.y - c.y) * theta.cos() + c.y;
         new_path.push(Point::new(q_x, q_y));
     }
 
@@ -140,10 +216,10 @@ fn rotate_by(path: &[Point], theta: f64) -> Vec<Point> {
 }
 
 fn bounding_box(path: &[Point]) -> Rect {
-    let mut min_x = INFINITY;
-    let mut min_y = INFINITY;
-    let mut max_x = -INFINITY;
-    let mut max_y = -INFINITY;
+    let mut min_x = f64::INFINITY;
+    let mut min_y = f64::INFINITY;
+    let mut max_x = -f64::INFINITY;
+    let mut max_y = -f64::INFINITY;
 
     for p in path {
         min_x = min_x.min(p.x);
@@ -194,11 +270,26 @@ fn translate_to(path: &[Point], pt: Point) -> Vec<Point> {
     new_points
 }
 
+/// Uniform scale to fit into `size`
+///
+/// Uniform scaling keeps thin strokes (1D gestures in terms of $1 extension, e.g.
+/// horizontal line) thin, they are fitted by the longer side, while zero side of
+/// perfectly straight line doesn't take part. Path of single point stays as is.
 fn scale_to(path: &[Point], size: Point) -> Vec<Point> {
     let bound = bounding_box(path);
     let mut new_points = Vec::new();
 
-    let scale = f64::min(size.x / bound.w, size.y / bound.h);
+    let side_scale = |target: f64, side: f64| {
+        if side > 0.0 {
+            target / side
+        } else {
+            f64::INFINITY
+        }
+    };
+    let scale = f64::min(side_scale(size.x, bound.w), side_scale(size.y, bound.h));
+    if !scale.is_finite() {
+        return path.to_vec();
+    }
 
     for p in path {
         let q_x = p.x * scale;
@@ -240,3 +331,272 @@ fn distance_at_best_angle(
 
     f64::min(f1, f2)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    /// Approximations of gesture classes of the $1 recognizer paper (Wobbrock et al., 2007),
+    /// not its published data set. Samples don't come from the templates: they are built
+    /// from rough outlines of the shapes, with random corners, pen speed, size, slant and
+    /// jitter, so the recognizer is checked on strokes it has never seen.
+    const TEMPLATES: &str = include_str!("../tests/fixtures/dollar_templates.json");
+    const SAMPLES: &str = include_str!("../tests/fixtures/dollar_samples.json");
+
+    #[derive(Deserialize)]
+    struct Gesture {
+        name: String,
+        points: Vec<[f64; 2]>,
+    }
+
+    fn load_gestures(raw: &str) -> Vec<Unistroke> {
+        let gestures: Vec<Gesture> = serde_yml::from_str(raw).unwrap();
+        gestures
+            .into_iter()
+            .map(|gesture| Unistroke {
+                name: gesture.name,
+                path: gesture
+                    .points
+                    .iter()
+                    .map(|[x, y]| Point::new(*x, *y))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn recognizer() -> UnistrokeRecognizer {
+        let mut recognizer = UnistrokeRecognizer::new(&RecognizerConfig::default());
+        for template in load_gestures(TEMPLATES) {
+            recognizer
+                .add_pattern(template.name, &template.path)
+                .unwrap();
+        }
+        recognizer
+    }
+
+    /// xorshift64*, enough for reproducible random input
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_f64(&mut self) -> f64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        fn range(&mut self, min: f64, max: f64) -> f64 {
+            min + (max - min) * self.next_f64()
+        }
+    }
+
+    fn rotate_around_origin(path: &[Point], theta: f64) -> Vec<Point> {
+        path.iter()
+            .map(|p| {
+                Point::new(
+                    p.x * theta.cos() - p.y * theta.sin(),
+                    p.x * theta.sin() + p.y * theta.cos(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn restricted_recognizer_picks_only_bound_patterns() {
+        let recognizer = recognizer();
+        let samples = load_gestures(SAMPLES);
+        let sample = samples.iter().find(|s| s.name == "circle").unwrap();
+
+        let restricted = recognizer.restricted(|name| name != "circle");
+        assert!(restricted.patterns.iter().all(|u| u.name != "circle"));
+        assert_eq!(restricted.patterns.len(), recognizer.patterns.len() - 1);
+
+        let (unistroke, _) = restricted.recognize_unistroke(&sample.path).unwrap();
+        assert_ne!(unistroke.name, "circle");
+    }
+
+    #[test]
+    fn samples_are_recognized_as_their_gestures() {
+        let recognizer = recognizer();
+        let treshold = RecognizerConfig::default().command_execute_treshold;
+
+        for sample in load_gestures(SAMPLES) {
+            let (unistroke, similarity) = recognizer.recognize_unistroke(&sample.path).unwrap();
+            assert_eq!(unistroke.name, sample.name, "similarity {similarity}");
+            assert!(
+                similarity >= treshold,
+                "{} recognized with similarity {}",
+                sample.name,
+                similarity
+            );
+        }
+    }
+
+    #[test]
+    fn recognition_is_invariant_to_translation() {
+        let recognizer = recognizer();
+        let mut rng = Rng(1);
+
+        for sample in load_gestures(SAMPLES) {
+            let (expected, expected_similarity) =
+                recognizer.recognize_unistroke(&sample.path).unwrap();
+            for _ in 0..4 {
+                let offset = Point::new(rng.range(-5000.0, 5000.0), rng.range(-5000.0, 5000.0));
+                let path = sample.path.iter().map(|p| p + offset).collect::<Vec<_>>();
+
+                let (unistroke, similarity) = recognizer.recognize_unistroke(&path).unwrap();
+                assert_eq!(unistroke.name, expected.name);
+                assert!((similarity - expected_similarity).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn recognition_is_invariant_to_uniform_scale() {
+        let recognizer = recognizer();
+        let mut rng = Rng(2);
+
+        for sample in load_gestures(SAMPLES) {
+            let (expected, expected_similarity) =
+                recognizer.recognize_unistroke(&sample.path).unwrap();
+            for _ in 0..4 {
+                let scale = rng.range(0.1, 10.0);
+                let path = sample.path.iter().map(|p| p * scale).collect::<Vec<_>>();
+
+                let (unistroke, similarity) = recognizer.recognize_unistroke(&path).unwrap();
+                assert_eq!(unistroke.name, expected.name, "scale {scale}");
+                assert!((similarity - expected_similarity).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn recognition_tolerates_small_rotations() {
+        let recognizer = recognizer();
+        let max_angle = RecognizerConfig::default().rotation_angle_range / 2.0;
+        let mut rng = Rng(3);
+
+        for sample in load_gestures(SAMPLES) {
+            for _ in 0..4 {
+                let angle = rng.range(-max_angle, max_angle);
+                let path = rotate_around_origin(&sample.path, degrees_to_radians(angle));
+
+                let (unistroke, _) = recognizer.recognize_unistroke(&path).unwrap();
+                assert_eq!(unistroke.name, sample.name, "rotated by {angle}°");
+            }
+        }
+    }
+
+    #[test]
+    fn resample_never_panics() {
+        let mut rng = Rng(4);
+
+        for _ in 0..2000 {
+            let point_count = rng.range(0.0, 40.0) as usize;
+            // repeated points and points far from each other are common rounding traps
+            let mut path = Vec::new();
+            for _ in 0..point_count {
+                let point = match rng.range(0.0, 4.0) as u32 {
+                    0 => path.last().copied().unwrap_or(Point::new(0.0, 0.0)),
+                    1 => Point::new(rng.range(-1e9, 1e9), rng.range(-1e9, 1e9)),
+                    2 => Point::new(rng.range(-1e-9, 1e-9), rng.range(-1e-9, 1e-9)),
+                    _ => Point::new(rng.range(0.0, 100.0), rng.range(0.0, 100.0)),
+                };
+                path.push(point);
+            }
+            let n = rng.range(0.0, 200.0) as u32;
+
+            let resampled = resample(&path, n);
+            let expected_len = if path.is_empty() { 0 } else { n as usize };
+            assert_eq!(resampled.len(), expected_len);
+        }
+    }
+
+    #[test]
+    fn resample_of_point_repeats_it() {
+        let point = Point::new(3.0, 4.0);
+        assert_eq!(resample(&[point, point], 8), vec![point; 8]);
+        assert_eq!(resample(&[point], 1), vec![point]);
+        assert!(resample(&[], 8).is_empty());
+    }
+
+    #[test]
+    fn rotation_keeps_centroid() {
+        let path = [
+            Point::new(10.0, 100.0),
+            Point::new(30.0, 140.0),
+            Point::new(50.0, 120.0),
+        ];
+
+        let rotated = rotate_by(&path, 1.0);
+        let (before, after) = (centroid(&path), centroid(&rotated));
+        assert!(before.distance(after) < 1e-9, "{before:?} != {after:?}");
+    }
+
+    #[test]
+    fn straight_lines_are_scaled_by_length() {
+        let recognizer = UnistrokeRecognizer::new(&RecognizerConfig::default());
+        let horizontal = [Point::new(10.0, 50.0), Point::new(310.0, 50.0)];
+        let vertical = [Point::new(40.0, 0.0), Point::new(40.0, 20.0)];
+
+        for line in [&horizontal[..], &vertical[..]] {
+            let normalized = recognizer.normalize_stroke_path(line).unwrap();
+            let bound = bounding_box(&normalized);
+            assert!(normalized
+                .iter()
+                .all(|p| p.x.is_finite() && p.y.is_finite()));
+            assert!((bound.w.max(bound.h) - 100.0).abs() < 1e-9);
+            assert_eq!(bound.w.min(bound.h), 0.0);
+        }
+    }
+
+    #[test]
+    fn thin_strokes_are_told_apart() {
+        let mut recognizer = UnistrokeRecognizer::new(&RecognizerConfig::default());
+        recognizer
+            .add_pattern(
+                "right".to_string(),
+                &[Point::new(0.0, 0.0), Point::new(100.0, 0.0)],
+            )
+            .unwrap();
+        recognizer
+            .add_pattern(
+                "down".to_string(),
+                &[Point::new(0.0, 0.0), Point::new(0.0, 100.0)],
+            )
+            .unwrap();
+
+        // slightly shaky horizontal stroke
+        let path = (0..30)
+            .map(|i| Point::new(i as f64 * 10.0, (i % 3) as f64))
+            .collect::<Vec<_>>();
+        let (unistroke, similarity) = recognizer.recognize_unistroke(&path).unwrap();
+        assert_eq!(unistroke.name, "right");
+        assert!(similarity > 0.9, "similarity {similarity}");
+    }
+
+    #[test]
+    fn degenerate_strokes_are_rejected() {
+        let recognizer = UnistrokeRecognizer::new(&RecognizerConfig::default());
+        let point = Point::new(5.0, 5.0);
+
+        assert_eq!(
+            recognizer.normalize_stroke_path(&[]),
+            Err(StrokeError::Empty)
+        );
+        assert_eq!(
+            recognizer.normalize_stroke_path(&[point; 20]),
+            Err(StrokeError::ZeroLength)
+        );
+        assert_eq!(
+            recognizer.normalize_stroke_path(&[point, Point::new(f64::NAN, 1.0)]),
+            Err(StrokeError::InvalidPoint)
+        );
+        assert_eq!(
+            recognizer.normalize_stroke_path(&[Point::new(-1e308, 0.0), Point::new(1e308, 0.0)]),
+            Err(StrokeError::InvalidPoint)
+        );
+    }
+}
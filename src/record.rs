@@ -0,0 +1,270 @@
+use crate::{
+    capture::GestureSource,
+    config::AppConfig,
+    patterns::{
+        gesture_file_path, load_gestures, pattern_names, save_gesture, validate_pattern_name,
+        PointerGesture,
+    },
+    recognizer::{Point, UnistrokeRecognizer},
+};
+
+/// Record pattern from one or several samples drawn in a row
+///
+/// Samples that not similar enough to the rest (by `command_execute_treshold`) considered
+/// failed attempts and dropped, so single bad stroke doesn't become part of the pattern.
+pub fn record_pattern(
+    config: &AppConfig,
+    name: &str,
+    sample_count: usize,
+    force: bool,
+) -> Result<(), ()> {
+    if PointerGesture::from_name(name).is_some() {
+        eprintln!(
+            "ERROR: name {} is reserved for wheel and rocker gesture",
+            name
+        );
+        return Err(());
+    }
+
+    if let Err(reason) = validate_pattern_name(name) {
+        eprintln!("ERROR: invalid pattern name {:?}, {}", name, reason);
+        return Err(());
+    }
+
+    let gesture_file_path = gesture_file_path(name)?;
+
+    if gesture_file_path.exists() && !force {
+        eprintln!(
+            "ERROR: pattern with name {} already exist, use --force flag if you want override it",
+            name
+        );
+        return Err(());
+    }
+
+    let point_count_treshold = config.recognizer.point_count_treshold as usize;
+    let recognizer = UnistrokeRecognizer::new(&config.recognizer);
+    let mut samples = Vec::new();
+    let mut normalized = Vec::new();
+    {
+        let mut capture = GestureSource::new(config)?;
+        if sample_count > 1 {
+            capture.set_counter(0, sample_count);
+        }
+
+        while samples.len() < sample_count {
+            let Some(gesture_path) = capture.next_gesture() else {
+                if let Some(reason) = capture.cancel_reason() {
+                    eprintln!("recording cancelled, reason: {}", reason.description());
+                }
+                return Ok(());
+            };
+
+            if gesture_path.len() < point_count_treshold {
+                eprintln!("skip gesture saving, reason: pattern point count less than specified in config ({})", point_count_treshold);
+                if sample_count == 1 {
+                    return Err(());
+                }
+                continue;
+            }
+
+            let normalized_path = match recognizer.normalize_stroke_path(&gesture_path) {
+                Ok(normalized_path) => normalized_path,
+                Err(err) => {
+                    eprintln!("skip gesture saving, reason: {}", err.description());
+                    if sample_count == 1 {
+                        return Err(());
+                    }
+                    continue;
+                }
+            };
+
+            samples.push(gesture_path);
+            normalized.push(normalized_path);
+            if sample_count > 1 {
+                eprintln!("captured sample {}/{}", samples.len(), sample_count);
+                capture.set_counter(samples.len(), sample_count);
+            }
+        }
+    }
+
+    let samples = reject_outliers(config, &recognizer, samples, &normalized);
+    if samples.is_empty() {
+        eprintln!("ERROR: samples are too different from each other, nothing saved");
+        return Err(());
+    }
+
+    warn_similar_patterns(config, recognizer, name, &samples)?;
+
+    save_gesture(&gesture_file_path, &samples)?;
+
+    if sample_count > 1 {
+        eprintln!("saved {} sample(s) of pattern {}", samples.len(), name);
+    }
+
+    Ok(())
+}
+
+/// Samples compared to the medoid (one most similar to all others), not to mean of the
+/// rest, so single bad stroke doesn't drag down similarity of the good samples
+fn reject_outliers(
+    config: &AppConfig,
+    recognizer: &UnistrokeRecognizer,
+    samples: Vec<Vec<Point>>,
+    normalized: &[Vec<Point>],
+) -> Vec<Vec<Point>> {
+    if samples.len() < 2 {
+        return samples;
+    }
+
+    let similarity_sum = |index: usize| -> f64 {
+        normalized
+            .iter()
+            .enumerate()
+            .filter(|(other_index, _)| *other_index != index)
+            .map(|(_, other)| recognizer.similarity(&normalized[index], other))
+            .sum()
+    };
+    let medoid = (0..normalized.len())
+        .map(|index| (index, similarity_sum(index)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+        .unwrap_or(0);
+
+    let mut accepted = Vec::new();
+    for (index, sample) in samples.into_iter().enumerate() {
+        let similarity = recognizer.similarity(&normalized[index], &normalized[medoid]);
+        if index != medoid && similarity < config.recognizer.command_execute_treshold {
+            eprintln!(
+                "reject sample {}, reason: similarity to sample {} ≈ {:.02} less than specified in config ({})",
+                index + 1,
+                medoid + 1,
+                similarity,
+                config.recognizer.command_execute_treshold
+            );
+            continue;
+        }
+
+        accepted.push(sample);
+    }
+
+    accepted
+}
+
+/// Existing pattern that close to new one will be confused with it during recognition
+fn warn_similar_patterns(
+    config: &AppConfig,
+    mut recognizer: UnistrokeRecognizer,
+    name: &str,
+    samples: &[Vec<Point>],
+) -> Result<(), ()> {
+    let names = pattern_names()?;
+    let existing = load_gestures(names.iter().map(String::as_str).filter(|n| *n != name))?;
+    if existing.is_empty() {
+        return Ok(());
+    }
+
+    // broken samples of existing patterns can't be confused with anything
+    for unistroke in &existing {
+        let _ = recognizer.add_pattern(unistroke.name.clone(), &unistroke.path);
+    }
+    if recognizer.patterns.is_empty() {
+        return Ok(());
+    }
+
+    let mut closest: Option<(&str, f64)> = None;
+    for sample in samples {
+        let Ok((unistroke, similarity)) = recognizer.recognize_unistroke(sample) else {
+            continue;
+        };
+        if closest.is_none_or(|(_, best)| similarity > best) {
+            closest = Some((&unistroke.name, similarity));
+        }
+    }
+
+    if let Some((closest_name, similarity)) = closest {
+        if similarity >= config.recognizer.command_execute_treshold {
+            eprintln!(
+                "WARNING: pattern {} is too close to existing pattern {} (similarity ≈ {:.02}), one may be recognized as another",
+                name, closest_name, similarity
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(from: (f64, f64), to: (f64, f64)) -> Vec<Point> {
+        (0..20)
+            .map(|i| {
+                let t = i as f64 / 19.0;
+                Point::new(from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t)
+            })
+            .collect()
+    }
+
+    /// Similar lines with circle inserted at `outlier` position
+    fn samples_with_outlier(good: usize, outlier: usize) -> Vec<Vec<Point>> {
+        let circle = (0..20)
+            .map(|i| {
+                let angle = i as f64 / 19.0 * 6.0;
+                Point::new(angle.cos() * 50.0, angle.sin() * 50.0)
+            })
+            .collect();
+        let mut samples = (0..good)
+            .map(|i| line((i as f64, 0.0), (100.0, 100.0 + i as f64)))
+            .collect::<Vec<_>>();
+        samples.insert(outlier, circle);
+        samples
+    }
+
+    fn assert_outlier_rejected(good: usize, outlier: usize) {
+        let config = AppConfig::default();
+        let recognizer = UnistrokeRecognizer::new(&config.recognizer);
+
+        let samples = samples_with_outlier(good, outlier);
+        let normalized = samples
+            .iter()
+            .map(|sample| recognizer.normalize_stroke_path(sample).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut expected = samples.clone();
+        expected.remove(outlier);
+        assert_eq!(
+            reject_outliers(&config, &recognizer, samples, &normalized),
+            expected
+        );
+    }
+
+    #[test]
+    fn outlier_sample_is_rejected() {
+        assert_outlier_rejected(6, 2);
+    }
+
+    #[test]
+    fn outlier_among_two_good_samples_is_rejected() {
+        assert_outlier_rejected(2, 0);
+        assert_outlier_rejected(2, 2);
+    }
+
+    #[test]
+    fn outlier_among_four_good_samples_is_rejected() {
+        assert_outlier_rejected(4, 1);
+    }
+
+    #[test]
+    fn single_sample_is_kept() {
+        let config = AppConfig::default();
+        let recognizer = UnistrokeRecognizer::new(&config.recognizer);
+        let samples = vec![line((0.0, 0.0), (100.0, 0.0))];
+        let normalized = vec![recognizer.normalize_stroke_path(&samples[0]).unwrap()];
+
+        assert_eq!(
+            reject_outliers(&config, &recognizer, samples.clone(), &normalized),
+            samples
+        );
+    }
+}
@@ -1,15 +1,30 @@
-use std::convert::TryInto;
+use std::{
+    collections::HashSet,
+    convert::TryInto,
+    io::ErrorKind,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
+use cgmath::MetricSpace;
 use smithay_client_toolkit::{
     compositor::{CompositorHandler, CompositorState},
     delegate_compositor, delegate_keyboard, delegate_layer, delegate_output, delegate_pointer,
-    delegate_registry, delegate_seat, delegate_shm,
-    output::{OutputHandler, OutputState},
+    delegate_pointer_constraints, delegate_registry, delegate_relative_pointer, delegate_seat,
+    delegate_shm, delegate_xdg_shell, delegate_xdg_window,
+    globals::ProvidesBoundGlobal,
+    output::{OutputHandler, OutputInfo, OutputState},
     registry::{ProvidesRegistryState, RegistryState},
     registry_handlers,
     seat::{
         keyboard::{KeyEvent, KeyboardHandler, Keysym, Modifiers},
-        pointer::{PointerEvent, PointerEventKind, PointerHandler},
+        pointer::{
+            cursor_shape::CursorShapeManager, AxisScroll, PointerEvent, PointerEventKind,
+            PointerHandler,
+        },
+        pointer_constraints::{PointerConstraintsHandler, PointerConstraintsState},
+        relative_pointer::{RelativeMotionEvent, RelativePointerHandler, RelativePointerState},
         Capability, SeatHandler, SeatState,
     },
     shell::{
@@ -17,6 +32,10 @@ use smithay_client_toolkit::{
             Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
             LayerSurfaceConfigure,
         },
+        xdg::{
+            window::{Window, WindowConfigure, WindowDecorations, WindowHandler},
+            XdgShell,
+        },
         WaylandSurface,
     },
     shm::{
@@ -25,57 +44,365 @@ use smithay_client_toolkit::{
     },
 };
 use wayland_client::{
+    backend::WaylandError,
+    delegate_noop,
     globals::registry_queue_init,
     protocol::{wl_keyboard, wl_output, wl_pointer, wl_seat, wl_shm, wl_surface},
-    Connection, QueueHandle,
+    Connection, Dispatch, EventQueue, QueueHandle,
+};
+use wayland_protocols::wp::{
+    cursor_shape::v1::client::wp_cursor_shape_device_v1::{Shape, WpCursorShapeDeviceV1},
+    fractional_scale::v1::client::{
+        wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+        wp_fractional_scale_v1::{self, WpFractionalScaleV1},
+    },
+    pointer_constraints::zv1::client::{
+        zwp_confined_pointer_v1::ZwpConfinedPointerV1, zwp_locked_pointer_v1::ZwpLockedPointerV1,
+        zwp_pointer_constraints_v1::Lifetime,
+    },
+    relative_pointer::zv1::client::zwp_relative_pointer_v1::ZwpRelativePointerV1,
+    viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter},
 };
 
-use crate::recognizer::Point;
+use crate::{
+    cheatsheet::{draw_cheatsheet, CheatsheetEntry},
+    config::{
+        CancelTrigger, CaptureConfig, CursorShape, EndTrigger, GestureCommand, KeyboardMode,
+        OverlayConfig, OverlayMode, StartTrigger,
+    },
+    draw::{Canvas, Color, GRAY, SHADE, TRANSPARENT, WHITE},
+    font::text_size,
+    patterns::PointerGesture,
+    recognizer::{Point, UnistrokeRecognizer},
+};
+
+/// Overlay that can capture several gestures in a row
+pub struct GestureCapture {
+    event_queue: EventQueue<AppData>,
+    app: AppData,
+}
 
-pub fn get_user_gesture() -> Option<Vec<Point>> {
-    let conn = Connection::connect_to_env().unwrap();
+impl GestureCapture {
+    pub fn new(capture: &CaptureConfig, overlay: &OverlayConfig) -> Result<Self, ()> {
+        let conn = Connection::connect_to_env().map_err(|err| {
+            eprintln!("ERROR: couldn't connect to wayland compositor: {err}");
+        })?;
 
-    let (globals, mut event_queue) = registry_queue_init(&conn).unwrap();
-    let qh = event_queue.handle();
+        let (globals, event_queue) = registry_queue_init(&conn).map_err(|err| {
+            eprintln!("ERROR: couldn't get wayland globals: {err}");
+        })?;
+        let qh = event_queue.handle();
 
-    let compositor = CompositorState::bind(&globals, &qh).expect("wl_compositor is not available");
-    let layer_shell = LayerShell::bind(&globals, &qh).expect("layer shell is not available");
-    let shm = Shm::bind(&globals, &qh).expect("wl_shm is not available");
+        let compositor = CompositorState::bind(&globals, &qh).map_err(|_| {
+            eprintln!("ERROR: wl_compositor is not available");
+        })?;
+        // wlr-layer-shell is missing on GNOME and some other compositors
+        let shell = match LayerShell::bind(&globals, &qh) {
+            Ok(layer_shell) => Shell::Layer(layer_shell),
+            Err(_) => {
+                let xdg_shell = XdgShell::bind(&globals, &qh).map_err(|_| {
+                    eprintln!("ERROR: compositor supports neither wlr-layer-shell nor xdg-shell, overlay can't be shown");
+                })?;
+                eprintln!("WARNING: compositor doesn't support wlr-layer-shell, using fullscreen xdg_toplevel windows as overlay (keyboard option is ignored)");
+                Shell::Xdg(xdg_shell)
+            }
+        };
+        let shm = Shm::bind(&globals, &qh).map_err(|_| {
+            eprintln!("ERROR: wl_shm is not available");
+        })?;
 
-    let pool = SlotPool::new(256 * 256 * 4, &shm).expect("Failed to create pool");
+        let pool = SlotPool::new(256 * 256 * 4, &shm).map_err(|err| {
+            eprintln!("ERROR: failed to create shared memory pool: {err}");
+        })?;
 
-    let mut app = AppData {
-        registry_state: RegistryState::new(&globals),
-        seat_state: SeatState::new(&globals, &qh),
-        output_state: OutputState::new(&globals, &qh),
-        compositor,
-        layer_shell,
-        shm,
+        // fractional scaling needs both protocols, otherwise integer buffer scale is used
+        let fractional_scale = globals
+            .bind::<WpFractionalScaleManagerV1, _, _>(&qh, 1..=1, ())
+            .ok()
+            .zip(globals.bind::<WpViewporter, _, _>(&qh, 1..=1, ()).ok());
 
-        state: AppState::Run,
-        pool,
-        layers: Vec::new(),
-        keyboard: None,
-        pointer: None,
+        let app = AppData {
+            registry_state: RegistryState::new(&globals),
+            seat_state: SeatState::new(&globals, &qh),
+            output_state: OutputState::new(&globals, &qh),
+            relative_pointer_state: RelativePointerState::bind(&globals, &qh),
+            pointer_constraints_state: PointerConstraintsState::bind(&globals, &qh),
+            cursor_shape_manager: CursorShapeManager::bind(&globals, &qh).ok(),
+            compositor,
+            shell,
+            shm,
+            fractional_scale,
 
-        gesture_path: Vec::new(),
-    };
+            state: AppState::Run,
+            pool,
+            layers: Vec::new(),
+            keyboard: None,
+            pointer: None,
+            cursor_shape_device: None,
+            relative_pointer: None,
+            locked_pointer: None,
+            pointer_locked: false,
+
+            gesture_path: Vec::new(),
+            counter: None,
+            toast: None,
+            preview: None,
+            preview_recognizer: None,
+            preview_updated: None,
+            cheatsheet: Vec::new(),
+
+            idle_timeout: capture
+                .idle_timeout
+                .and_then(|timeout| Duration::try_from_secs_f64(timeout).ok()),
+            max_duration: capture
+                .max_duration
+                .and_then(|duration| Duration::try_from_secs_f64(duration).ok()),
+            capture: capture.clone(),
+            overlay: overlay.clone(),
+            capture_started: Instant::now(),
+            last_motion: Instant::now(),
+            modifiers_held: false,
+            modifiers_down: false,
+            drawing: false,
+            stroke_button: None,
+            held_start_used: false,
+            path_length: 0.0,
+            left_start: false,
+            cancel_reason: None,
+            pointer_gestures: HashSet::new(),
+            pointer_gesture: None,
+            origin: None,
+        };
+
+        Ok(GestureCapture { event_queue, app })
+    }
+
+    /// Show progress of multi-sample recording on the overlay
+    pub fn set_counter(&mut self, captured: usize, total: usize) {
+        self.app.counter = Some((captured, total));
+        self.app.draw();
+    }
+
+    /// Show label near `position` (global coordinates), e.g. at the end of the stroke
+    pub fn show_toast(&mut self, text: &str, position: Point) {
+        self.app.toast = Some(Label {
+            text: text.to_string(),
+            position,
+            color: WHITE,
+        });
+        self.app.draw();
+    }
+
+    /// Show thumbnails of patterns instead of capturing gesture
+    pub fn set_cheatsheet(&mut self, entries: Vec<CheatsheetEntry>) {
+        self.app.cheatsheet = entries;
+        self.app.draw();
+    }
 
-    loop {
-        match app.state {
-            AppState::Run => {
-                event_queue.blocking_dispatch(&mut app).unwrap();
+    /// Show best candidate next to the cursor while gesture is drawn
+    pub fn set_preview(&mut self, preview: LivePreview) {
+        self.app.preview = Some(preview);
+    }
+
+    /// Keep overlay on screen for `duration` without blocking caller,
+    /// overlay is closed earlier if user presses Escape
+    pub fn close_after(mut self, duration: Duration) -> thread::JoinHandle<()> {
+        self.app.state = AppState::Run;
+        self.app.preview = None;
+
+        thread::spawn(move || {
+            let deadline = Instant::now() + duration;
+            while Instant::now() < deadline {
+                if let AppState::Exit = self.app.state {
+                    break;
+                }
+
+                if self.dispatch_available().is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(10));
             }
-            AppState::Exit => {
-                return None;
+        })
+    }
+
+    /// Dispatch events that already arrived, returns number of dispatched events
+    fn dispatch_available(&mut self) -> Result<usize, ()> {
+        self.event_queue.flush().map_err(|_| ())?;
+
+        if let Some(guard) = self.event_queue.prepare_read() {
+            match guard.read() {
+                Ok(_) => {}
+                Err(WaylandError::Io(err)) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(_) => return Err(()),
             }
-            AppState::ExitRecognize => {
-                return Some(app.gesture_path);
+        }
+
+        self.event_queue
+            .dispatch_pending(&mut self.app)
+            .map_err(|_| ())
+    }
+
+    /// Finish capture when one of these gestures is made while drawing
+    pub fn set_pointer_gestures(&mut self, gestures: HashSet<PointerGesture>) {
+        self.app.pointer_gestures = gestures;
+    }
+
+    /// Wheel or rocker gesture that finished the last capture
+    pub fn pointer_gesture(&self) -> Option<PointerGesture> {
+        self.app.pointer_gesture
+    }
+
+    /// Output and position where the last gesture was started
+    pub fn origin(&self) -> Option<&StrokeOrigin> {
+        self.app.origin.as_ref()
+    }
+
+    /// Why the last gesture was cancelled, `None` for Escape or closed overlay
+    pub fn cancel_reason(&self) -> Option<CancelReason> {
+        self.app.cancel_reason
+    }
+
+    /// Wait for the next gesture, `None` if capture was cancelled
+    pub fn next_gesture(&mut self) -> Option<Vec<Point>> {
+        self.app.state = AppState::Run;
+        self.app.cancel_reason = None;
+        self.app.pointer_gesture = None;
+        self.app.origin = None;
+        self.app.preview_recognizer = None;
+        self.app.preview_updated = None;
+        // release that finished the previous gesture shouldn't finish this one
+        self.app.modifiers_held = self.app.modifiers_down;
+        self.app.drawing = false;
+        // button was released at the end of the previous sample
+        if self.app.capture.start_on == StartTrigger::Held && !self.app.held_start_used {
+            self.app.held_start_used = true;
+            self.app.start_drawing();
+        } else {
+            self.app.gesture_path.clear();
+        }
+        if self.app.toast.take().is_some() {
+            self.app.draw();
+        }
+
+        loop {
+            match self.app.state {
+                AppState::Run => match self.app.deadline() {
+                    None => {
+                        self.event_queue.blocking_dispatch(&mut self.app).unwrap();
+                    }
+                    Some(deadline) if Instant::now() >= deadline => {
+                        self.app.state = AppState::ExitRecognize;
+                    }
+                    // there is no blocking dispatch with timeout, so events are polled
+                    Some(deadline) => {
+                        if self.dispatch_available().expect("wayland dispatch failed") == 0 {
+                            let remaining = deadline.saturating_duration_since(Instant::now());
+                            thread::sleep(remaining.min(Duration::from_millis(10)));
+                        }
+                    }
+                },
+                AppState::Exit => {
+                    self.app.unlock_pointer();
+                    return None;
+                }
+                AppState::ExitRecognize => {
+                    self.app.unlock_pointer();
+                    return Some(std::mem::take(&mut self.app.gesture_path));
+                }
             }
         }
     }
 }
 
+/// Where the stroke was started, used to pick binding restricted to output or region
+#[derive(Debug, Clone)]
+pub struct StrokeOrigin {
+    /// Output name, e.g. `DP-1`
+    pub output: Option<String>,
+    /// Start point relative to the output
+    pub position: Point,
+    /// Logical size of the output
+    pub output_size: (u32, u32),
+}
+
+impl StrokeOrigin {
+    pub fn accepts(&self, cmd: &GestureCommand) -> bool {
+        let on_output = cmd
+            .on_output
+            .as_ref()
+            .is_none_or(|name| self.output.as_ref() == Some(name));
+        let in_region = cmd.start_region.is_none_or(|region| {
+            region.contains(self.position.x, self.position.y, self.output_size)
+        });
+
+        on_output && in_region
+    }
+}
+
+/// Bindings that may run for stroke started at `origin`, stroke of unknown origin takes
+/// only unrestricted ones
+pub fn bindings_for<'a>(
+    origin: Option<&StrokeOrigin>,
+    commands: &'a [GestureCommand],
+) -> Vec<&'a GestureCommand> {
+    commands
+        .iter()
+        .filter(|cmd| match origin {
+            Some(origin) => origin.accepts(cmd),
+            None => !cmd.is_restricted(),
+        })
+        .collect()
+}
+
+/// Recognition of unfinished gesture
+pub struct LivePreview {
+    pub recognizer: Arc<UnistrokeRecognizer>,
+    /// Configured bindings, preview names only patterns bound where gesture started
+    pub commands: Vec<GestureCommand>,
+    /// Similarity required to run command, weaker candidates are grayed out
+    pub treshold: f64,
+    /// Point count of gesture before the first preview
+    pub min_points: usize,
+    /// Minimal time between recognitions
+    pub interval: Duration,
+}
+
+struct Label {
+    text: String,
+    /// Global position
+    position: Point,
+    color: Color,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CancelReason {
+    SecondButton,
+    Scroll,
+    ReturnToStart,
+    MaxPathLength,
+}
+
+impl CancelReason {
+    /// Exit code of wlrune, so scripts can tell cancellation reasons apart
+    pub fn exit_code(self) -> i32 {
+        match self {
+            CancelReason::SecondButton => 3,
+            CancelReason::Scroll => 4,
+            CancelReason::ReturnToStart => 5,
+            CancelReason::MaxPathLength => 6,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            CancelReason::SecondButton => "another button pressed",
+            CancelReason::Scroll => "scrolled",
+            CancelReason::ReturnToStart => "pointer returned to the start point",
+            CancelReason::MaxPathLength => "path is longer than capture.max_path_length",
+        }
+    }
+}
+
 enum AppState {
     Run,
     Exit,
@@ -86,25 +413,658 @@ struct AppData {
     registry_state: RegistryState,
     seat_state: SeatState,
     output_state: OutputState,
+    relative_pointer_state: RelativePointerState,
+    pointer_constraints_state: PointerConstraintsState,
+    cursor_shape_manager: Option<CursorShapeManager>,
     compositor: CompositorState,
-    layer_shell: LayerShell,
+    shell: Shell,
     shm: Shm,
+    fractional_scale: Option<(WpFractionalScaleManagerV1, WpViewporter)>,
 
     state: AppState,
     pool: SlotPool,
     layers: Vec<OutputLayer>,
     keyboard: Option<wl_keyboard::WlKeyboard>,
     pointer: Option<wl_pointer::WlPointer>,
+    cursor_shape_device: Option<WpCursorShapeDeviceV1>,
+    /// Present only when relative pointer capture is enabled and supported
+    relative_pointer: Option<ZwpRelativePointerV1>,
+    locked_pointer: Option<ZwpLockedPointerV1>,
+    /// Compositor activated the lock, path is built from relative motion
+    pointer_locked: bool,
 
     gesture_path: Vec<Point>,
+    /// Captured and total sample count of recording session
+    counter: Option<(usize, usize)>,
+    toast: Option<Label>,
+    preview: Option<LivePreview>,
+    /// Preview recognizer restricted to bindings of the current gesture origin
+    preview_recognizer: Option<UnistrokeRecognizer>,
+    preview_updated: Option<Instant>,
+    cheatsheet: Vec<CheatsheetEntry>,
+
+    capture: CaptureConfig,
+    overlay: OverlayConfig,
+    idle_timeout: Option<Duration>,
+    max_duration: Option<Duration>,
+    capture_started: Instant,
+    last_motion: Instant,
+    /// Gesture is being drawn, motion events are recorded
+    drawing: bool,
+    /// Button pressed on the overlay to start the stroke, `None` if it was held before
+    stroke_button: Option<u32>,
+    /// Button held at launch started the first gesture, the next ones start with a press
+    held_start_used: bool,
+    /// Length of gesture path in pixels
+    path_length: f64,
+    /// Pointer went far enough from the start point to come back to it
+    left_start: bool,
+    cancel_reason: Option<CancelReason>,
+    /// Some keyboard modifier was held since gesture start
+    modifiers_held: bool,
+    /// Some keyboard modifier is held right now
+    modifiers_down: bool,
+    /// Wheel and rocker gestures bound to commands
+    pointer_gestures: HashSet<PointerGesture>,
+    pointer_gesture: Option<PointerGesture>,
+    origin: Option<StrokeOrigin>,
+}
+
+/// Protocol used to put overlay on top of everything
+enum Shell {
+    Layer(LayerShell),
+    /// Fallback for compositors without wlr-layer-shell
+    Xdg(XdgShell),
+}
+
+enum OverlaySurface {
+    Layer(LayerSurface),
+    Window(Window),
+}
+
+impl OverlaySurface {
+    fn wl_surface(&self) -> &wl_surface::WlSurface {
+        match self {
+            OverlaySurface::Layer(layer) => layer.wl_surface(),
+            OverlaySurface::Window(window) => window.wl_surface(),
+        }
+    }
+
+    fn commit(&self) {
+        match self {
+            OverlaySurface::Layer(layer) => layer.commit(),
+            OverlaySurface::Window(window) => window.commit(),
+        }
+    }
 }
 
 #[allow(dead_code)]
 struct OutputLayer {
-    layer: LayerSurface,
+    output: wl_output::WlOutput,
+    layer: OverlaySurface,
     logical_size: (u32, u32),
     logical_position: (i32, i32),
+    /// Size from the last configure event, layer can't be drawn before it
+    configured_size: Option<(u32, u32)>,
     buffer: Option<Buffer>,
+    /// Toast plate drawn in the buffer as x, y, width and height in logical pixels
+    toast_area: Option<Area>,
+    /// Device pixels per logical pixel, preferred by compositor for this surface
+    scale: f64,
+    /// Present when compositor supports fractional scaling, buffer is then
+    /// mapped to logical size with viewport instead of integer buffer scale
+    fractional_scale: Option<(WpFractionalScaleV1, WpViewport)>,
+}
+
+impl Drop for OutputLayer {
+    fn drop(&mut self) {
+        if let Some((fractional_scale, viewport)) = &self.fractional_scale {
+            fractional_scale.destroy();
+            viewport.destroy();
+        }
+    }
+}
+
+impl AppData {
+    fn draw(&mut self) {
+        for index in 0..self.layers.len() {
+            self.draw_layer(index);
+        }
+    }
+
+    fn draw_layer(&mut self, index: usize) {
+        let out_layer = &mut self.layers[index];
+        let Some((width, height)) = out_layer.configured_size else {
+            return;
+        };
+
+        // buffer is allocated at device resolution, canvas takes care of scaling
+        let buffer_width = (width as f64 * out_layer.scale).round() as u32;
+        let buffer_height = (height as f64 * out_layer.scale).round() as u32;
+        let stride = buffer_width as i32 * 4;
+        let (buffer, pixels) = self
+            .pool
+            .create_buffer(
+                buffer_width as i32,
+                buffer_height as i32,
+                stride,
+                wl_shm::Format::Argb8888,
+            )
+            .expect("create buffer");
+
+        let mut canvas = Canvas::new(pixels, buffer_width, buffer_height, out_layer.scale);
+        canvas.clear(TRANSPARENT);
+
+        out_layer.toast_area = None;
+        // invisible overlay only receives pointer events, nothing is drawn on it
+        if self.overlay.mode == OverlayMode::Visible {
+            if !self.cheatsheet.is_empty() {
+                draw_cheatsheet(&mut canvas, &self.cheatsheet);
+            }
+
+            if let Some((captured, total)) = self.counter {
+                draw_counter(&mut canvas, captured, total);
+            }
+
+            if let Some(label) = &self.toast {
+                out_layer.toast_area = draw_label(&mut canvas, label, out_layer.logical_position);
+            }
+        }
+
+        let wl_surface = out_layer.layer.wl_surface();
+        match &out_layer.fractional_scale {
+            Some((_, viewport)) => viewport.set_destination(width as i32, height as i32),
+            None => wl_surface.set_buffer_scale(out_layer.scale as i32),
+        }
+        buffer.attach_to(wl_surface).expect("buffer attach");
+        wl_surface.damage_buffer(0, 0, buffer_width as i32, buffer_height as i32);
+        out_layer.layer.commit();
+
+        out_layer.buffer = Some(buffer);
+    }
+
+    /// Repaint just the toast, e.g. new candidate of live preview, layer is drawn
+    /// entirely if compositor still holds its buffer
+    fn redraw_toast(&mut self) {
+        for index in 0..self.layers.len() {
+            if !self.repaint_toast(index) {
+                self.draw_layer(index);
+            }
+        }
+    }
+
+    /// Clear the old toast and draw the new one in the last buffer of the layer,
+    /// `false` if the buffer can't be reused
+    fn repaint_toast(&mut self, index: usize) -> bool {
+        if self.overlay.mode != OverlayMode::Visible || !self.cheatsheet.is_empty() {
+            return false;
+        }
+
+        let out_layer = &mut self.layers[index];
+        let (Some((width, height)), Some(buffer)) = (out_layer.configured_size, &out_layer.buffer)
+        else {
+            return false;
+        };
+        let buffer_width = (width as f64 * out_layer.scale).round() as u32;
+        let buffer_height = (height as f64 * out_layer.scale).round() as u32;
+        if buffer.height() != buffer_height as i32 || buffer.stride() != buffer_width as i32 * 4 {
+            return false;
+        }
+        let Some(pixels) = buffer.canvas(&mut self.pool) else {
+            return false;
+        };
+
+        let mut canvas = Canvas::new(pixels, buffer_width, buffer_height, out_layer.scale);
+        let old_area = out_layer.toast_area.take();
+        if let Some((x, y, width, height)) = old_area {
+            canvas.fill_rect(x, y, width, height, TRANSPARENT);
+        }
+        // cleared plate may have covered the counter
+        if let Some((captured, total)) = self.counter {
+            draw_counter(&mut canvas, captured, total);
+        }
+        out_layer.toast_area = self
+            .toast
+            .as_ref()
+            .and_then(|label| draw_label(&mut canvas, label, out_layer.logical_position));
+
+        let wl_surface = out_layer.layer.wl_surface();
+        buffer.attach_to(wl_surface).expect("buffer attach");
+        for (x, y, width, height) in [old_area, out_layer.toast_area].into_iter().flatten() {
+            wl_surface.damage(x, y, width, height);
+        }
+        out_layer.layer.commit();
+
+        true
+    }
+
+    /// Create overlay on output, skipped while output has no logical geometry yet
+    fn add_output_layer(&mut self, qh: &QueueHandle<Self>, output: wl_output::WlOutput) {
+        let Some((logical_size, logical_position)) = self
+            .output_state
+            .info(&output)
+            .and_then(|info| output_geometry(&info))
+        else {
+            return;
+        };
+
+        let surface = self.compositor.create_surface(qh);
+        let layer = match &self.shell {
+            Shell::Layer(layer_shell) => {
+                let layer = layer_shell.create_layer_surface(
+                    qh,
+                    surface,
+                    Layer::Overlay,
+                    Some("simple_layer"),
+                    Some(&output),
+                );
+                layer.set_anchor(Anchor::BOTTOM);
+                layer.set_keyboard_interactivity(match self.overlay.keyboard {
+                    KeyboardMode::Exclusive => KeyboardInteractivity::Exclusive,
+                    KeyboardMode::OnDemand => KeyboardInteractivity::OnDemand,
+                    KeyboardMode::None => KeyboardInteractivity::None,
+                });
+                layer.set_size(logical_size.0, logical_size.1);
+                OverlaySurface::Layer(layer)
+            }
+            Shell::Xdg(xdg_shell) => {
+                let window = xdg_shell.create_window(surface, WindowDecorations::None, qh);
+                window.set_title("wlrune");
+                window.set_app_id("wlrune");
+                window.set_fullscreen(Some(&output));
+                OverlaySurface::Window(window)
+            }
+        };
+
+        let fractional_scale = self.fractional_scale.as_ref().map(|(manager, viewporter)| {
+            let wl_surface = layer.wl_surface();
+            (
+                manager.get_fractional_scale(wl_surface, qh, wl_surface.clone()),
+                viewporter.get_viewport(wl_surface, qh, ()),
+            )
+        });
+        layer.commit();
+
+        self.layers.push(OutputLayer {
+            output,
+            layer,
+            logical_size,
+            logical_position,
+            configured_size: None,
+            buffer: None,
+            toast_area: None,
+            scale: 1.0,
+            fractional_scale,
+        });
+    }
+
+    fn overlay_configured(
+        &mut self,
+        qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        size: Option<(u32, u32)>,
+    ) {
+        // layer may be already removed together with its output
+        let Some(index) = self
+            .layers
+            .iter()
+            .position(|l| l.layer.wl_surface() == surface)
+        else {
+            return;
+        };
+
+        let out_layer = &mut self.layers[index];
+        out_layer.configured_size = Some(size.unwrap_or(out_layer.logical_size));
+
+        surface.frame(qh, surface.clone());
+        self.draw_layer(index);
+    }
+
+    fn overlay_closed(&mut self, surface: &wl_surface::WlSurface) {
+        // compositor closes layer when its output is gone, capture goes on while
+        // there is at least one layer left
+        self.layers.retain(|l| l.layer.wl_surface() != surface);
+        if self.layers.is_empty() {
+            self.state = AppState::Exit;
+        }
+    }
+
+    fn start_drawing(&mut self) {
+        self.drawing = true;
+        self.stroke_button = None;
+        self.gesture_path.clear();
+        self.path_length = 0.0;
+        self.left_start = false;
+        self.capture_started = Instant::now();
+        self.last_motion = Instant::now();
+    }
+
+    /// Surface local position to global one
+    fn global_position(
+        &self,
+        surface: &wl_surface::WlSurface,
+        position: (f64, f64),
+    ) -> Option<Point> {
+        let layer = self
+            .layers
+            .iter()
+            .find(|l| l.layer.wl_surface() == surface)?;
+
+        Some(Point::new(
+            layer.logical_position.0 as f64 + position.0,
+            layer.logical_position.1 as f64 + position.1,
+        ))
+    }
+
+    /// Output under global position
+    fn stroke_origin(&self, point: Point) -> Option<StrokeOrigin> {
+        let layer = self.layers.iter().find(|l| {
+            let (x, y) = l.logical_position;
+            let (width, height) = l.logical_size;
+            point.x >= x as f64
+                && point.y >= y as f64
+                && point.x < x as f64 + width as f64
+                && point.y < y as f64 + height as f64
+        })?;
+
+        Some(StrokeOrigin {
+            output: self
+                .output_state
+                .info(&layer.output)
+                .and_then(|info| info.name),
+            position: Point::new(
+                point.x - layer.logical_position.0 as f64,
+                point.y - layer.logical_position.1 as f64,
+            ),
+            output_size: layer.logical_size,
+        })
+    }
+
+    /// Time when gesture is finished by timeout
+    fn deadline(&self) -> Option<Instant> {
+        // timeouts are counted from the stroke start
+        if !self.drawing {
+            return None;
+        }
+
+        let idle_deadline = self.idle_timeout.map(|timeout| self.last_motion + timeout);
+        let duration_deadline = self
+            .max_duration
+            .map(|duration| self.capture_started + duration);
+
+        match (idle_deadline, duration_deadline) {
+            (Some(lhs), Some(rhs)) => Some(lhs.min(rhs)),
+            (lhs, rhs) => lhs.or(rhs),
+        }
+    }
+
+    /// Finish capture by bound wheel or rocker gesture, `false` if gesture isn't bound
+    fn finish_pointer_gesture(&mut self, gesture: Option<PointerGesture>) -> bool {
+        let Some(gesture) = gesture.filter(|gesture| self.pointer_gestures.contains(gesture))
+        else {
+            return false;
+        };
+
+        self.pointer_gesture = Some(gesture);
+        self.state = AppState::ExitRecognize;
+        true
+    }
+
+    fn cancel(&mut self, reason: CancelReason) {
+        self.cancel_reason = Some(reason);
+        self.state = AppState::Exit;
+    }
+
+    /// Add point to gesture path and check triggers that depend on path shape
+    fn push_point(&mut self, point: Point) {
+        match self.gesture_path.last() {
+            Some(last) => self.path_length += last.distance(point),
+            None => self.origin = self.stroke_origin(point),
+        }
+        self.gesture_path.push(point);
+
+        if self
+            .capture
+            .max_path_length
+            .is_some_and(|max_length| self.path_length > max_length)
+        {
+            self.cancel(CancelReason::MaxPathLength);
+            return;
+        }
+
+        if self
+            .capture
+            .cancel_on
+            .contains(&CancelTrigger::ReturnToStart)
+        {
+            let radius = self.capture.return_radius;
+            let distance = self.gesture_path[0].distance(point);
+            // pointer should go noticeably further than the radius, otherwise jitter
+            // at the start point cancels gesture
+            if distance > 2.0 * radius {
+                self.left_start = true;
+            } else if self.left_start && distance < radius {
+                self.cancel(CancelReason::ReturnToStart);
+            }
+        }
+    }
+
+    fn is_trigger_button(&self, button: u32) -> bool {
+        self.capture
+            .button
+            .is_none_or(|trigger| trigger.code() == button)
+    }
+
+    /// Button that draws the stroke, either configured trigger or the one that started it
+    fn is_stroke_button(&self, button: u32) -> bool {
+        self.stroke_button == Some(button)
+            || self
+                .capture
+                .button
+                .is_some_and(|trigger| trigger.code() == button)
+    }
+
+    /// Cursor is set by surface under pointer, so it's done on each enter
+    fn set_cursor(&self, serial: u32) {
+        let Some(cursor) = self.overlay.cursor else {
+            return;
+        };
+
+        match cursor_shape(cursor) {
+            Some(shape) => {
+                if let Some(device) = &self.cursor_shape_device {
+                    device.set_shape(serial, shape);
+                }
+            }
+            None => {
+                if let Some(pointer) = &self.pointer {
+                    pointer.set_cursor(serial, None, 0, 0);
+                }
+            }
+        }
+    }
+
+    /// Keep pointer in place while gesture is drawn, see `relative_pointer_motion`
+    fn lock_pointer(&mut self, qh: &QueueHandle<Self>, surface: &wl_surface::WlSurface) {
+        if self.relative_pointer.is_none() || self.locked_pointer.is_some() {
+            return;
+        }
+        let Some(pointer) = &self.pointer else {
+            return;
+        };
+
+        self.locked_pointer = self
+            .pointer_constraints_state
+            .lock_pointer(surface, pointer, None, Lifetime::Oneshot, qh)
+            .ok();
+    }
+
+    fn unlock_pointer(&mut self) {
+        if let Some(locked_pointer) = self.locked_pointer.take() {
+            locked_pointer.destroy();
+        }
+        self.pointer_locked = false;
+    }
+
+    fn update_preview(&mut self) {
+        let Some(preview) = &self.preview else {
+            return;
+        };
+        if self.gesture_path.len() < preview.min_points.max(2) {
+            return;
+        }
+        if self
+            .preview_updated
+            .is_some_and(|updated| updated.elapsed() < preview.interval)
+        {
+            return;
+        }
+        self.preview_updated = Some(Instant::now());
+
+        // origin is known since the first point of the stroke
+        let recognizer = self.preview_recognizer.get_or_insert_with(|| {
+            let bindings = bindings_for(self.origin.as_ref(), &preview.commands);
+            preview
+                .recognizer
+                .restricted(|name| bindings.iter().any(|cmd| cmd.pattern == name))
+        });
+        if recognizer.patterns.is_empty() {
+            return;
+        }
+
+        // e.g. pointer hasn't moved yet
+        let Ok((unistroke, similarity)) = recognizer.recognize_unistroke(&self.gesture_path) else {
+            return;
+        };
+        let label = Label {
+            text: format!("{} {:.02}", unistroke.name, similarity),
+            position: self.gesture_path[self.gesture_path.len() - 1],
+            color: if similarity >= preview.treshold {
+                WHITE
+            } else {
+                GRAY
+            },
+        };
+        // label stays where candidate was found until it changes
+        if self
+            .toast
+            .as_ref()
+            .is_some_and(|toast| toast.text == label.text && toast.color == label.color)
+        {
+            return;
+        }
+        self.toast = Some(label);
+        self.redraw_toast();
+    }
+}
+
+/// Shape from cursor-shape protocol, `None` for hidden cursor
+fn cursor_shape(cursor: CursorShape) -> Option<Shape> {
+    match cursor {
+        CursorShape::Default => Some(Shape::Default),
+        CursorShape::Crosshair => Some(Shape::Crosshair),
+        CursorShape::Cell => Some(Shape::Cell),
+        CursorShape::Pointer => Some(Shape::Pointer),
+        CursorShape::Move => Some(Shape::Move),
+        CursorShape::Grab => Some(Shape::Grab),
+        CursorShape::Grabbing => Some(Shape::Grabbing),
+        CursorShape::Hidden => None,
+    }
+}
+
+/// Logical size and position of output, both are unknown until output is configured
+fn output_geometry(info: &OutputInfo) -> Option<((u32, u32), (i32, i32))> {
+    let (width, height) = info.logical_size?;
+    let size = (width.try_into().ok()?, height.try_into().ok()?);
+    if size.0 == 0 || size.1 == 0 {
+        return None;
+    }
+
+    Some((size, info.logical_position?))
+}
+
+/// Row of boxes at the top of the output, filled ones are already captured samples
+fn draw_counter(canvas: &mut Canvas, captured: usize, total: usize) {
+    const BOX_SIZE: i32 = 16;
+    const GAP: i32 = 8;
+
+    let total = total as i32;
+    let row_width = total * BOX_SIZE + (total - 1) * GAP;
+    let x = (canvas.width() - row_width) / 2;
+    let y = 2 * BOX_SIZE;
+
+    for i in 0..total {
+        let box_x = x + i * (BOX_SIZE + GAP);
+        if i < captured as i32 {
+            canvas.fill_rect(box_x, y, BOX_SIZE, BOX_SIZE, WHITE);
+        } else {
+            canvas.stroke_rect(box_x, y, BOX_SIZE, BOX_SIZE, 2, WHITE);
+        }
+    }
+}
+
+/// Scroll direction, `None` for events without movement (e.g. end of kinetic scroll)
+fn wheel_gesture(horizontal: &AxisScroll, vertical: &AxisScroll) -> Option<PointerGesture> {
+    let gesture = if vertical.absolute.abs() >= horizontal.absolute.abs() {
+        match vertical.absolute {
+            value if value < 0.0 => PointerGesture::WheelUp,
+            value if value > 0.0 => PointerGesture::WheelDown,
+            _ => return None,
+        }
+    } else if horizontal.absolute < 0.0 {
+        PointerGesture::WheelLeft
+    } else {
+        PointerGesture::WheelRight
+    };
+
+    Some(gesture)
+}
+
+/// Rectangle as x, y, width and height
+type Area = (i32, i32, i32, i32);
+
+/// Draw toast on layer at `logical_position`, returns area of the plate if it's on the layer
+fn draw_label(canvas: &mut Canvas, label: &Label, logical_position: (i32, i32)) -> Option<Area> {
+    let x = label.position.x - logical_position.0 as f64;
+    let y = label.position.y - logical_position.1 as f64;
+    if x < 0.0 || y < 0.0 || x >= canvas.width() as f64 || y >= canvas.height() as f64 {
+        return None;
+    }
+
+    Some(draw_toast(
+        canvas,
+        x as i32,
+        y as i32,
+        &label.text,
+        label.color,
+    ))
+}
+
+/// Label on dark plate placed below right of the point, kept inside of the output
+fn draw_toast(canvas: &mut Canvas, x: i32, y: i32, text: &str, color: Color) -> Area {
+    const SCALE: i32 = 3;
+    const PADDING: i32 = 8;
+    const OFFSET: i32 = 16;
+
+    let (text_width, text_height) = text_size(text, SCALE);
+    let plate_width = text_width + 2 * PADDING;
+    let plate_height = text_height + 2 * PADDING;
+
+    let mut plate_x = x + OFFSET;
+    let mut plate_y = y + OFFSET;
+    if plate_x + plate_width > canvas.width() {
+        plate_x = x - OFFSET - plate_width;
+    }
+    if plate_y + plate_height > canvas.height() {
+        plate_y = y - OFFSET - plate_height;
+    }
+    plate_x = plate_x.max(0);
+    plate_y = plate_y.max(0);
+
+    canvas.fill_rect(plate_x, plate_y, plate_width, plate_height, SHADE);
+    canvas.draw_text(plate_x + PADDING, plate_y + PADDING, SCALE, text, color);
+
+    (plate_x, plate_y, plate_width, plate_height)
 }
 
 impl CompositorHandler for AppData {
@@ -112,10 +1072,24 @@ impl CompositorHandler for AppData {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _surface: &wl_surface::WlSurface,
-        _new_factor: i32,
+        surface: &wl_surface::WlSurface,
+        new_factor: i32,
     ) {
-        // Not needed for this example.
+        let Some(index) = self
+            .layers
+            .iter()
+            .position(|l| l.layer.wl_surface() == surface)
+        else {
+            return;
+        };
+
+        // preferred fractional scale is more precise than integer one
+        if self.layers[index].fractional_scale.is_some() {
+            return;
+        }
+
+        self.layers[index].scale = new_factor as f64;
+        self.draw_layer(index);
     }
 
     fn transform_changed(
@@ -135,60 +1109,6 @@ impl CompositorHandler for AppData {
         _surface: &wl_surface::WlSurface,
         _time: u32,
     ) {
-        //let Some(layer) = self
-        //    .layers
-        //    .iter_mut()
-        //    .find(|l| *l.layer.wl_surface() == *surface)
-        //else {
-        //    return;
-        //};
-        //
-        //let (width, height) = layer.logical_size;
-        //let stride = width as i32 * 4;
-        //
-        //let buffer = layer.buffer.get_or_insert_with(|| {
-        //    self.pool
-        //        .create_buffer(
-        //            width as i32,
-        //            height as i32,
-        //            stride,
-        //            wl_shm::Format::Argb8888,
-        //        )
-        //        .expect("create buffer")
-        //        .0
-        //});
-        //
-        //let mut canvas = match self.pool.canvas(buffer) {
-        //    Some(canvas) => canvas,
-        //    None => {
-        //        // This should be rare, but if the compositor has not released the previous
-        //        // buffer, we need double-buffering.
-        //        let (width, height) = layer.logical_size;
-        //        let (second_buffer, canvas) = self
-        //            .pool
-        //            .create_buffer(
-        //                width as i32,
-        //                height as i32,
-        //                stride,
-        //                wl_shm::Format::Argb8888,
-        //            )
-        //            .expect("create buffer");
-        //        *buffer = second_buffer;
-        //        canvas
-        //    }
-        //};
-        //
-        //canvas.write_all(layer.pixels.as_bytes()).unwrap();
-        //
-        //let wl_surface = layer.layer.wl_surface();
-        //
-        //// Damage the entire window
-        //wl_surface.damage_buffer(0, 0, width as i32, height as i32);
-        //// Request our next frame
-        //wl_surface.frame(qh, wl_surface.clone());
-        //// Attach and commit to present.
-        //buffer.attach_to(wl_surface).expect("buffer attach");
-        //layer.layer.commit();
     }
 
     fn surface_enter(
@@ -221,60 +1141,60 @@ impl OutputHandler for AppData {
         qh: &QueueHandle<Self>,
         output: wl_output::WlOutput,
     ) {
-        let info = self.output_state.info(&output).unwrap();
-
-        let logical_size: (u32, u32) = {
-            let (w, h) = info.logical_size.unwrap();
-            (w.try_into().unwrap(), h.try_into().unwrap())
-        };
-
-        let logical_position: (i32, i32) = info.logical_position.unwrap();
-
-        let surface = self.compositor.create_surface(&qh);
-        let layer = self.layer_shell.create_layer_surface(
-            &qh,
-            surface,
-            Layer::Overlay,
-            Some("simple_layer"),
-            Some(&output),
-        );
-        layer.set_anchor(Anchor::BOTTOM);
-        layer.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
-        layer.set_size(logical_size.0, logical_size.1);
-        layer.commit();
-
-        //let pixels: ImageBuffer<Rgba<u8>, Vec<u8>> =
-        //    ImageBuffer::new(logical_size.0, logical_size.1);
-
-        self.layers.push(OutputLayer {
-            layer,
-            logical_size,
-            logical_position,
-            //pixels,
-            buffer: None,
-        });
+        // output may be still configured, then layer is created on the first update
+        self.add_output_layer(qh, output);
     }
 
     fn update_output(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
     ) {
+        let Some(index) = self.layers.iter().position(|l| l.output == output) else {
+            self.add_output_layer(qh, output);
+            return;
+        };
+
+        let Some((logical_size, logical_position)) = self
+            .output_state
+            .info(&output)
+            .and_then(|info| output_geometry(&info))
+        else {
+            return;
+        };
+
+        let out_layer = &mut self.layers[index];
+        out_layer.logical_position = logical_position;
+        if out_layer.logical_size != logical_size {
+            // layer is redrawn when compositor acknowledges new size with configure
+            out_layer.logical_size = logical_size;
+            // fullscreen window follows output size by itself
+            if let OverlaySurface::Layer(layer) = &out_layer.layer {
+                layer.set_size(logical_size.0, logical_size.1);
+                layer.commit();
+            }
+        } else {
+            self.draw_layer(index);
+        }
     }
 
     fn output_destroyed(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _output: wl_output::WlOutput,
+        output: wl_output::WlOutput,
     ) {
+        self.layers.retain(|l| l.output != output);
+        if self.layers.is_empty() {
+            self.state = AppState::Exit;
+        }
     }
 }
 
 impl LayerShellHandler for AppData {
-    fn closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _layer: &LayerSurface) {
-        self.state = AppState::Exit;
+    fn closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, layer: &LayerSurface) {
+        self.overlay_closed(layer.wl_surface());
     }
 
     fn configure(
@@ -285,38 +1205,29 @@ impl LayerShellHandler for AppData {
         configure: LayerSurfaceConfigure,
         _serial: u32,
     ) {
-        let out_layer = self
-            .layers
-            .iter_mut()
-            .find(|e| &e.layer == layer)
-            .expect("failed to find related layer");
-
-        let (width, height) = configure.new_size;
-
-        if let Some(buffer) = out_layer.buffer.take() {
-            buffer.wl_buffer().destroy();
-        }
-
-        let stride = width as i32 * 4;
-        let (buffer, _canvas) = self
-            .pool
-            .create_buffer(
-                width as i32,
-                height as i32,
-                stride,
-                wl_shm::Format::Argb8888,
-            )
-            .expect("create buffer");
-
-        buffer.attach_to(layer.wl_surface()).expect("buffer attach");
+        self.overlay_configured(qh, layer.wl_surface(), Some(configure.new_size));
+    }
+}
 
-        out_layer.buffer = Some(buffer);
+impl WindowHandler for AppData {
+    fn request_close(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, window: &Window) {
+        self.overlay_closed(window.wl_surface());
+    }
 
-        layer
-            .wl_surface()
-            .damage_buffer(0, 0, width as i32, height as i32);
-        layer.wl_surface().frame(qh, layer.wl_surface().clone());
-        layer.commit();
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        window: &Window,
+        configure: WindowConfigure,
+        _serial: u32,
+    ) {
+        // without suggested size window covers the whole output
+        let size = match configure.new_size {
+            (Some(width), Some(height)) => Some((width.get(), height.get())),
+            _ => None,
+        };
+        self.overlay_configured(qh, window.wl_surface(), size);
     }
 }
 
@@ -347,6 +1258,28 @@ impl SeatHandler for AppData {
                 .seat_state
                 .get_pointer(qh, &seat)
                 .expect("Failed to create pointer");
+
+            let shape_needed = self.overlay.cursor.and_then(cursor_shape).is_some();
+            if shape_needed {
+                match &self.cursor_shape_manager {
+                    Some(manager) => {
+                        self.cursor_shape_device = Some(manager.get_shape_device(&pointer, qh))
+                    }
+                    None => eprintln!("WARNING: compositor doesn't support cursor shape protocol, default cursor is used"),
+                }
+            }
+
+            if self.capture.relative_pointer {
+                let relative_pointer = self
+                    .relative_pointer_state
+                    .get_relative_pointer(&pointer, qh)
+                    .ok()
+                    .filter(|_| self.pointer_constraints_state.bound_global().is_ok());
+                if relative_pointer.is_none() {
+                    eprintln!("WARNING: compositor doesn't support relative pointer or pointer constraints, absolute positions are used");
+                }
+                self.relative_pointer = relative_pointer;
+            }
             self.pointer = Some(pointer);
         }
     }
@@ -365,6 +1298,13 @@ impl SeatHandler for AppData {
         }
 
         if capability == Capability::Pointer {
+            if let Some(device) = self.cursor_shape_device.take() {
+                device.destroy();
+            }
+            if let Some(relative_pointer) = self.relative_pointer.take() {
+                relative_pointer.destroy();
+            }
+            self.unlock_pointer();
             if let Some(pointer) = self.pointer.take() {
                 pointer.release();
             }
@@ -430,12 +1370,17 @@ impl KeyboardHandler for AppData {
         _: &QueueHandle<Self>,
         _: &wl_keyboard::WlKeyboard,
         _serial: u32,
-        _modifiers: Modifiers,
+        modifiers: Modifiers,
         _layout: u32,
     ) {
-        //if !modifiers.alt && !modifiers.shift && !modifiers.alt && !modifiers.logo {
-        //    self.state = AppState::ExitRecognize;
-        //}
+        let held = modifiers.ctrl || modifiers.alt || modifiers.shift || modifiers.logo;
+        self.modifiers_down = held;
+        if held {
+            self.modifiers_held = true;
+        } else if self.modifiers_held && self.capture.end_on.contains(&EndTrigger::ModifierRelease)
+        {
+            self.state = AppState::ExitRecognize;
+        }
     }
 }
 
@@ -443,12 +1388,33 @@ impl PointerHandler for AppData {
     fn pointer_frame(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
+        qh: &QueueHandle<Self>,
         _pointer: &wl_pointer::WlPointer,
         events: &[PointerEvent],
     ) {
         for event in events {
+            // events after cancellation shouldn't finish gesture
+            if let AppState::Exit = self.state {
+                break;
+            }
+
+            if let PointerEventKind::Enter { serial } = event.kind {
+                self.set_cursor(serial);
+
+                // overlay appears under the cursor, that's where held button started stroke
+                if self.drawing && self.gesture_path.is_empty() {
+                    if let Some(point) = self.global_position(&event.surface, event.position) {
+                        self.push_point(point);
+                    }
+                }
+            }
+
             if let PointerEventKind::Motion { .. } = event.kind {
+                // locked pointer doesn't move, relative motion is used instead
+                if !self.drawing || self.pointer_locked {
+                    continue;
+                }
+
                 let Some(layer) = self
                     .layers
                     .iter_mut()
@@ -459,26 +1425,148 @@ impl PointerHandler for AppData {
 
                 let (x, y) = event.position;
 
-                //draw_circle(
-                //    &mut layer.pixels,
-                //    x as i32,
-                //    y as i32,
-                //    5,
-                //    Rgba::from([0xFF; 4]),
-                //);
-
-                //println!("mouse local position: x={}, y={}", x, y);
                 let global_x = layer.logical_position.0 as f64 + x;
                 let global_y = layer.logical_position.1 as f64 + y;
-                //println!("mouse global position: x={}, y={}", global_x, global_y);
 
-                self.gesture_path.push(Point::new(global_x, global_y));
+                self.push_point(Point::new(global_x, global_y));
+                self.last_motion = Instant::now();
             }
 
-            if let PointerEventKind::Release { .. } = event.kind {
-                self.state = AppState::ExitRecognize;
+            if let PointerEventKind::Press { button, .. } = event.kind {
+                if !self.drawing {
+                    if self.is_trigger_button(button) {
+                        self.start_drawing();
+                        self.stroke_button = Some(button);
+                        if let Some(point) = self.global_position(&event.surface, event.position) {
+                            self.push_point(point);
+                        }
+                    }
+                    continue;
+                }
+
+                if self.finish_pointer_gesture(PointerGesture::rocker(button)) {
+                    continue;
+                }
+
+                if self.capture.end_on.contains(&EndTrigger::SecondClick)
+                    && self.is_trigger_button(button)
+                {
+                    self.state = AppState::ExitRecognize;
+                } else if !self.is_stroke_button(button)
+                    && self
+                        .capture
+                        .cancel_on
+                        .contains(&CancelTrigger::SecondButton)
+                {
+                    self.cancel(CancelReason::SecondButton);
+                }
+            }
+
+            if let PointerEventKind::Axis {
+                horizontal,
+                vertical,
+                ..
+            } = &event.kind
+            {
+                let gesture = wheel_gesture(horizontal, vertical);
+                if !self.drawing || gesture.is_none() || self.finish_pointer_gesture(gesture) {
+                    continue;
+                }
+
+                if self.capture.cancel_on.contains(&CancelTrigger::Scroll) {
+                    self.cancel(CancelReason::Scroll);
+                }
+            }
+
+            if let PointerEventKind::Release { button, .. } = event.kind {
+                if self.drawing
+                    && self.capture.end_on.contains(&EndTrigger::Release)
+                    && self.is_trigger_button(button)
+                {
+                    self.state = AppState::ExitRecognize;
+                }
             }
         }
+
+        if let AppState::Run = self.state {
+            if let Some(event) = events.last().filter(|_| self.drawing) {
+                self.lock_pointer(qh, &event.surface);
+            }
+            self.update_preview();
+        }
+    }
+}
+
+impl RelativePointerHandler for AppData {
+    fn relative_pointer_motion(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _relative_pointer: &ZwpRelativePointerV1,
+        _pointer: &wl_pointer::WlPointer,
+        event: RelativeMotionEvent,
+    ) {
+        if !self.pointer_locked || !self.drawing || !matches!(self.state, AppState::Run) {
+            return;
+        }
+
+        // path continues from the point where pointer was locked, so it may go
+        // beyond the output
+        let Some(last) = self.gesture_path.last().copied() else {
+            return;
+        };
+        let (dx, dy) = event.delta_unaccel;
+        self.push_point(Point::new(last.x + dx, last.y + dy));
+        self.last_motion = Instant::now();
+
+        if let AppState::Run = self.state {
+            self.update_preview();
+        }
+    }
+}
+
+impl PointerConstraintsHandler for AppData {
+    fn confined(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _confined_pointer: &ZwpConfinedPointerV1,
+        _surface: &wl_surface::WlSurface,
+        _pointer: &wl_pointer::WlPointer,
+    ) {
+    }
+
+    fn unconfined(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _confined_pointer: &ZwpConfinedPointerV1,
+        _surface: &wl_surface::WlSurface,
+        _pointer: &wl_pointer::WlPointer,
+    ) {
+    }
+
+    fn locked(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _locked_pointer: &ZwpLockedPointerV1,
+        _surface: &wl_surface::WlSurface,
+        _pointer: &wl_pointer::WlPointer,
+    ) {
+        self.pointer_locked = true;
+    }
+
+    fn unlocked(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _locked_pointer: &ZwpLockedPointerV1,
+        _surface: &wl_surface::WlSurface,
+        _pointer: &wl_pointer::WlPointer,
+    ) {
+        // oneshot lock is gone, absolute motion is used for the rest of the gesture
+        self.pointer_locked = false;
     }
 }
 
@@ -495,109 +1583,49 @@ delegate_shm!(AppData);
 delegate_seat!(AppData);
 delegate_keyboard!(AppData);
 delegate_pointer!(AppData);
+delegate_relative_pointer!(AppData);
+delegate_pointer_constraints!(AppData);
 
 delegate_layer!(AppData);
+delegate_xdg_shell!(AppData);
+delegate_xdg_window!(AppData);
 
 delegate_registry!(AppData);
 
+delegate_noop!(AppData: WpFractionalScaleManagerV1);
+delegate_noop!(AppData: WpViewporter);
+delegate_noop!(AppData: WpViewport);
+
+impl Dispatch<WpFractionalScaleV1, wl_surface::WlSurface> for AppData {
+    fn event(
+        state: &mut Self,
+        _proxy: &WpFractionalScaleV1,
+        event: wp_fractional_scale_v1::Event,
+        surface: &wl_surface::WlSurface,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let wp_fractional_scale_v1::Event::PreferredScale { scale } = event else {
+            return;
+        };
+
+        let Some(index) = state
+            .layers
+            .iter()
+            .position(|l| l.layer.wl_surface() == surface)
+        else {
+            return;
+        };
+
+        // scale is sent as numerator of fraction with denominator 120
+        state.layers[index].scale = scale as f64 / 120.0;
+        state.draw_layer(index);
+    }
+}
+
 impl ProvidesRegistryState for AppData {
     fn registry(&mut self) -> &mut RegistryState {
         &mut self.registry_state
     }
     registry_handlers![OutputState, SeatState];
 }
-
-//fn draw_pattern(canvas: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, path: &[Point], color: Rgba<u8>) {
-//    for (index, point) in path.iter().enumerate() {
-//        draw_circle(
-//            canvas,
-//            (point.x * 4.0) as i32 + 1500,
-//            (point.y * 4.0) as i32 + 700,
-//            (2 + 5 * index / path.len()) as i32,
-//            color,
-//        );
-//    }
-//}
-//
-//fn draw_circle(
-//    canvas: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
-//    cx: i32,
-//    cy: i32,
-//    radius: i32,
-//    color: Rgba<u8>,
-//) {
-//    let mut d = 3 - 2 * radius;
-//    let (mut x, mut y) = (radius, 0);
-//
-//    while y <= x {
-//        draw_line(canvas, cx - x, cy - y, cx + x, cy - y, color);
-//        draw_line(canvas, cx - x, cy + y, cx + x, cy + y, color);
-//        draw_line(canvas, cx - y, cy + x, cx + y, cy + x, color);
-//        draw_line(canvas, cx - y, cy - x, cx + y, cy - x, color);
-//
-//        if d <= 0 {
-//            d += 4 * y + 6;
-//            y += 1;
-//        } else {
-//            d += 4 * (y - x) + 10;
-//            x -= 1;
-//            y += 1;
-//        }
-//    }
-//}
-//
-//fn draw_line(
-//    canvas: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
-//    mut x0: i32,
-//    mut y0: i32,
-//    mut x1: i32,
-//    mut y1: i32,
-//    color: Rgba<u8>,
-//) {
-//    let steep = (y1 - y0).abs() > (x1 - x0).abs();
-//
-//    if steep {
-//        swap(&mut x0, &mut y0);
-//        swap(&mut x1, &mut y1);
-//    }
-//
-//    if x0 > x1 {
-//        swap(&mut x0, &mut x1);
-//        swap(&mut y0, &mut y1);
-//    }
-//
-//    let delta_x = (x1 - x0).abs();
-//    let delta_y = (y1 - y0).abs();
-//    let mut error = delta_x / 2;
-//    let mut y = y0;
-//    let y_step = if y0 < y1 { 1 } else { -1 };
-//
-//    for x in x0..=x1 {
-//        let (new_x, new_y) = if steep { (y, x) } else { (x, y) };
-//
-//        if canvas.in_bounds(new_x as u32, new_y as u32) {
-//            canvas.put_pixel(new_x as u32, new_y as u32, color);
-//        }
-//
-//        error -= delta_y;
-//        if error < 0 {
-//            y += y_step;
-//            error += delta_x;
-//        }
-//    }
-//}
-//
-//fn draw_rect(
-//    canvas: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
-//    x: i32,
-//    y: i32,
-//    width: i32,
-//    height: i32,
-//    color: Rgba<u8>,
-//) {
-//    for x in x..x + width {
-//        for y in y..y + height {
-//            canvas.put_pixel(x as u32, y as u32, color);
-//        }
-//    }
-//}
@@ -0,0 +1,226 @@
+//! Gesture capture against the scripted compositor, strokes are checked through
+//! patterns saved by `wlrune record` and exit of `wlrune recognize`
+
+mod compositor;
+mod session;
+
+use compositor::{Compositor, Output, BTN_LEFT, BTN_RIGHT, KEY_ESC};
+use session::Session;
+
+const CONFIG: &str = "notifications:\n  enabled: false\n";
+
+fn outputs() -> Vec<Output> {
+    vec![
+        Output {
+            name: "DP-1",
+            position: (0, 0),
+            size: (1920, 1080),
+        },
+        Output {
+            name: "DP-2",
+            position: (1920, 0),
+            size: (1280, 720),
+        },
+    ]
+}
+
+/// Diagonal stroke of 12 points in surface local coordinates
+fn stroke() -> Vec<(f64, f64)> {
+    (0..12)
+        .map(|i| (100.0 + 12.5 * i as f64, 200.0 + 7.25 * i as f64))
+        .collect()
+}
+
+#[test]
+fn held_button_stroke_is_recorded_in_global_coordinates() {
+    let mut compositor =
+        Compositor::spawn(outputs(), false, CONFIG, &["record", "--name", "diagonal"]);
+    compositor.wait_for_overlay();
+
+    let stroke = stroke();
+    compositor.pointer_enter(1, stroke[0]);
+    for &point in &stroke[1..] {
+        compositor.pointer_motion(point);
+    }
+    compositor.pointer_button(BTN_RIGHT, false);
+
+    let outcome = compositor.finish();
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+
+    let expected = stroke.iter().map(|&(x, y)| (x + 1920.0, y)).collect();
+    assert_eq!(compositor.pattern("diagonal"), Some(vec![expected]));
+}
+
+#[test]
+fn second_held_sample_starts_with_press() {
+    let config = format!("{CONFIG}capture:\n  cancel_on: [second_button]\n");
+    let mut compositor = Compositor::spawn(
+        outputs(),
+        false,
+        &config,
+        &["record", "--name", "twice", "--samples", "2"],
+    );
+    compositor.wait_for_overlay();
+
+    let stroke = stroke();
+    compositor.pointer_enter(0, stroke[0]);
+    for &point in &stroke[1..] {
+        compositor.pointer_motion(point);
+    }
+    compositor.pointer_button(BTN_RIGHT, false);
+    // counter shows the first sample
+    compositor.wait_for_redraw();
+
+    // hover after release isn't recorded and the press doesn't cancel recording
+    compositor.pointer_motion((10.0, 10.0));
+    compositor.pointer_motion(stroke[0]);
+    compositor.pointer_button(BTN_RIGHT, true);
+    for &point in &stroke[1..] {
+        compositor.pointer_motion(point);
+    }
+    compositor.pointer_button(BTN_RIGHT, false);
+
+    let outcome = compositor.finish();
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert_eq!(
+        compositor.pattern("twice"),
+        Some(vec![stroke.clone(), stroke]),
+        "{}",
+        outcome.stderr
+    );
+}
+
+#[test]
+fn press_trigger_starts_stroke_at_press_position() {
+    let config = format!("{CONFIG}capture:\n  start_on: press\n  button: left\n");
+    let mut compositor =
+        Compositor::spawn(outputs(), false, &config, &["record", "--name", "pressed"]);
+    compositor.wait_for_overlay();
+
+    let stroke = stroke();
+    // motion before the press and other buttons aren't part of the stroke
+    compositor.pointer_enter(0, (10.0, 10.0));
+    compositor.pointer_motion((20.0, 20.0));
+    compositor.pointer_button(BTN_RIGHT, true);
+    compositor.pointer_button(BTN_RIGHT, false);
+    compositor.pointer_motion(stroke[0]);
+    compositor.pointer_button(BTN_LEFT, true);
+    for &point in &stroke[1..] {
+        compositor.pointer_motion(point);
+    }
+    compositor.pointer_button(BTN_LEFT, false);
+
+    let outcome = compositor.finish();
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert_eq!(compositor.pattern("pressed"), Some(vec![stroke]));
+}
+
+#[test]
+fn escape_cancels_recording() {
+    let mut compositor =
+        Compositor::spawn(outputs(), true, CONFIG, &["record", "--name", "escaped"]);
+    compositor.wait_for_overlay();
+
+    let stroke = stroke();
+    compositor.pointer_enter(0, stroke[0]);
+    for &point in &stroke[1..] {
+        compositor.pointer_motion(point);
+    }
+    compositor.key(KEY_ESC);
+
+    let outcome = compositor.finish();
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert_eq!(compositor.pattern("escaped"), None);
+}
+
+#[test]
+fn second_button_cancels_recognition_with_exit_code() {
+    let config = format!(
+        "{CONFIG}capture:\n  cancel_on: [second_button]\ncommands:\n  - pattern: wheel_up\n    command: \"true\"\n"
+    );
+    let mut compositor = Compositor::spawn(outputs(), false, &config, &["recognize"]);
+    compositor.wait_for_overlay();
+
+    let stroke = stroke();
+    compositor.pointer_enter(0, stroke[0]);
+    for &point in &stroke[1..] {
+        compositor.pointer_motion(point);
+    }
+    compositor.pointer_button(BTN_LEFT, true);
+
+    let outcome = compositor.finish();
+    assert_eq!(outcome.status.code(), Some(3), "{}", outcome.stderr);
+}
+
+#[test]
+fn bound_wheel_gesture_finishes_recognition() {
+    let config = format!(
+        "{CONFIG}overlay:\n  toast_duration: 0\ncommands:\n  - pattern: wheel_up\n    command: \"true\"\n"
+    );
+    let mut compositor = Compositor::spawn(outputs(), false, &config, &["recognize"]);
+    compositor.wait_for_overlay();
+
+    let stroke = stroke();
+    compositor.pointer_enter(0, stroke[0]);
+    compositor.pointer_motion(stroke[1]);
+    compositor.pointer_scroll(-10.0);
+
+    let outcome = compositor.finish();
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert!(
+        outcome.stderr.contains("recognized as wheel_up"),
+        "{}",
+        outcome.stderr
+    );
+}
+
+#[test]
+fn unchanged_preview_candidate_isnt_redrawn() {
+    let config = format!(
+        "{CONFIG}overlay:\n  preview_interval: 0\nrecognizer:\n  point_count_treshold: 4\ncommands:\n  - pattern: diagonal\n    command: \"true\"\n"
+    );
+    let pattern = stroke()
+        .iter()
+        .map(|(x, y)| format!("{x} {y}\n"))
+        .collect::<String>();
+    let mut compositor = Compositor::spawn_with_patterns(
+        outputs(),
+        true,
+        &config,
+        &["recognize"],
+        &[("diagonal", &pattern)],
+    );
+    compositor.wait_for_overlay();
+
+    // every prefix of straight line is the same candidate with the same similarity
+    compositor.pointer_enter(0, (100.0, 200.0));
+    for i in 1..40 {
+        compositor.pointer_motion((100.0 + 5.0 * i as f64, 200.0 + 2.9 * i as f64));
+    }
+    // requests that aren't flushed yet are dropped on exit
+    compositor.wait_for_redraw();
+    compositor.key(KEY_ESC);
+
+    let outcome = compositor.finish();
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    // toast is drawn once on each output
+    assert_eq!(compositor.redraws(), 2, "{}", outcome.stderr);
+}
+
+#[test]
+fn missing_compositor_is_reported() {
+    let config = format!("{CONFIG}commands:\n  - pattern: wheel_up\n    command: \"true\"\n");
+    let session = Session::new(&config);
+
+    for args in [&["recognize"][..], &["record", "--name", "left"]] {
+        let outcome = session.run(args);
+        assert_eq!(outcome.status.code(), Some(1), "{}", outcome.stderr);
+        assert!(
+            outcome
+                .stderr
+                .contains("ERROR: couldn't connect to wayland compositor"),
+            "{}",
+            outcome.stderr
+        );
+    }
+}
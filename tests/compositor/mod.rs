@@ -0,0 +1,808 @@
+//! Scripted wayland compositor for capture tests
+//!
+//! wlrune is started as a child process connected to the socket of the test. The
+//! compositor speaks just enough of the wire protocol to map layer surfaces: it
+//! advertises `wl_compositor`, `wl_shm`, `zwlr_layer_shell_v1`, `wl_seat` and outputs
+//! with their xdg-output geometry, answers requests that need an answer and ignores
+//! the rest. Pointer and keyboard events are then injected by the test.
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    io::{ErrorKind, IoSlice, Read, Write},
+    os::{
+        fd::AsFd,
+        unix::net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rustix::net::{sendmsg, SendAncillaryBuffer, SendAncillaryMessage, SendFlags};
+
+pub const BTN_LEFT: u32 = 0x110;
+pub const BTN_RIGHT: u32 = 0x111;
+pub const KEY_ESC: u32 = 1;
+
+/// Time given to wlrune for every step, so broken capture fails test instead of hanging it
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Keymap with Escape only, enough for xkbcommon to compile it
+const KEYMAP: &str = r#"xkb_keymap {
+    xkb_keycodes "wlrune" { minimum = 8; maximum = 255; <ESC> = 9; };
+    xkb_types "wlrune" {
+        type "ONE_LEVEL" { modifiers = none; map[none] = Level1; level_name[Level1] = "Any"; };
+    };
+    xkb_compatibility "wlrune" { };
+    xkb_symbols "wlrune" { key <ESC> { type = "ONE_LEVEL", [ Escape ] }; };
+};
+"#;
+
+static SESSION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Output {
+    pub name: &'static str,
+    pub position: (i32, i32),
+    pub size: (i32, i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Global {
+    Compositor,
+    Shm,
+    LayerShell,
+    Seat,
+    XdgOutputManager,
+    Output(usize),
+}
+
+impl Global {
+    fn interface(self) -> (&'static str, u32) {
+        match self {
+            Global::Compositor => ("wl_compositor", 4),
+            Global::Shm => ("wl_shm", 1),
+            Global::LayerShell => ("zwlr_layer_shell_v1", 4),
+            Global::Seat => ("wl_seat", 7),
+            Global::XdgOutputManager => ("zxdg_output_manager_v1", 3),
+            Global::Output(_) => ("wl_output", 4),
+        }
+    }
+}
+
+/// Client objects, only ones whose requests are answered
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Object {
+    Display,
+    Registry,
+    Global(Global),
+    Surface,
+    LayerSurface { surface: u32 },
+    ShmPool,
+    Other,
+}
+
+#[derive(Default)]
+struct Surface {
+    output: Option<usize>,
+    /// Size requested by the layer surface
+    size: (i32, i32),
+    configured: bool,
+    buffer_attached: bool,
+    mapped: bool,
+}
+
+/// Finished wlrune process
+pub struct Outcome {
+    pub status: ExitStatus,
+    pub stderr: String,
+}
+
+pub struct Compositor {
+    dir: PathBuf,
+    child: Option<Child>,
+    stream: UnixStream,
+    received: Vec<u8>,
+    outputs: Vec<Output>,
+    keyboard: bool,
+    /// Object id and version
+    objects: HashMap<u32, (Object, u32)>,
+    surfaces: HashMap<u32, Surface>,
+    pointer: Option<u32>,
+    keyboard_object: Option<u32>,
+    /// Surface under the pointer
+    focus: Option<u32>,
+    keyboard_focus: Option<u32>,
+    serial: u32,
+    time: u32,
+    /// Commits of mapped layer surfaces, that is redraws of the overlay
+    redraws: usize,
+}
+
+impl Compositor {
+    /// Run wlrune with given config and arguments, connected to the new compositor
+    pub fn spawn(outputs: Vec<Output>, keyboard: bool, config: &str, args: &[&str]) -> Self {
+        Self::spawn_with_patterns(outputs, keyboard, config, args, &[])
+    }
+
+    /// Same as `spawn`, with gesture files saved before wlrune starts
+    pub fn spawn_with_patterns(
+        outputs: Vec<Output>,
+        keyboard: bool,
+        config: &str,
+        args: &[&str],
+        patterns: &[(&str, &str)],
+    ) -> Self {
+        let dir = env::temp_dir().join(format!(
+            "wlrune-test-{}-{}",
+            std::process::id(),
+            SESSION_COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let config_path = dir.join("config.yaml");
+        fs::write(&config_path, config).unwrap();
+
+        let patterns_dir = dir.join("data").join("wlrune").join("patterns");
+        fs::create_dir_all(&patterns_dir).unwrap();
+        for (name, content) in patterns {
+            fs::write(patterns_dir.join(name), content).unwrap();
+        }
+
+        let listener = UnixListener::bind(dir.join("wayland-0")).unwrap();
+        listener.set_nonblocking(true).unwrap();
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_wlrune"))
+            .arg("--config")
+            .arg(&config_path)
+            .args(args)
+            .env("XDG_RUNTIME_DIR", &dir)
+            .env("WAYLAND_DISPLAY", "wayland-0")
+            .env("HOME", &dir)
+            .env("XDG_DATA_HOME", dir.join("data"))
+            .env_remove("WAYLAND_SOCKET")
+            .env_remove("DBUS_SESSION_BUS_ADDRESS")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let started = Instant::now();
+        let stream = loop {
+            match listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    if started.elapsed() > TIMEOUT || child.try_wait().unwrap().is_some() {
+                        child.kill().ok();
+                        let output = child.wait_with_output().unwrap();
+                        panic!(
+                            "wlrune didn't connect to compositor:\n{}",
+                            String::from_utf8_lossy(&output.stderr)
+                        );
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(err) => panic!("accept failed: {err}"),
+            }
+        };
+        stream.set_nonblocking(false).unwrap();
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+
+        Compositor {
+            dir,
+            child: Some(child),
+            stream,
+            received: Vec::new(),
+            outputs,
+            keyboard,
+            objects: HashMap::from([(1, (Object::Display, 1))]),
+            surfaces: HashMap::new(),
+            pointer: None,
+            keyboard_object: None,
+            focus: None,
+            keyboard_focus: None,
+            serial: 0,
+            time: 0,
+            redraws: 0,
+        }
+    }
+
+    /// Handle requests until layer surface of every output is drawn
+    pub fn wait_for_overlay(&mut self) {
+        while !self.overlay_mapped() {
+            if !self.dispatch() {
+                self.fail("wlrune disconnected before overlay was mapped");
+            }
+        }
+    }
+
+    fn overlay_mapped(&self) -> bool {
+        let ready = (0..self.outputs.len()).all(|index| {
+            self.surfaces
+                .values()
+                .any(|surface| surface.output == Some(index) && surface.mapped)
+        });
+
+        ready && self.pointer.is_some() && (!self.keyboard || self.keyboard_object.is_some())
+    }
+
+    /// Number of times overlay was drawn after it was mapped, counted for every output
+    pub fn redraws(&self) -> usize {
+        self.redraws
+    }
+
+    /// Handle requests until overlay is drawn again, e.g. counter of recorded samples
+    pub fn wait_for_redraw(&mut self) {
+        let redraws = self.redraws;
+        while self.redraws == redraws {
+            if !self.dispatch() {
+                self.fail("wlrune disconnected before overlay was redrawn");
+            }
+        }
+    }
+
+    /// Pointer enters overlay of output at surface local position
+    pub fn pointer_enter(&mut self, output: usize, position: (f64, f64)) {
+        let surface = self.overlay_surface(output);
+        let pointer = self.pointer.unwrap();
+        let serial = self.next_serial();
+
+        self.focus = Some(surface);
+        self.send(
+            pointer,
+            0,
+            &[
+                Arg::Uint(serial),
+                Arg::Uint(surface),
+                Arg::Fixed(position.0),
+                Arg::Fixed(position.1),
+            ],
+        );
+        self.pointer_frame();
+    }
+
+    pub fn pointer_motion(&mut self, position: (f64, f64)) {
+        let pointer = self.pointer.unwrap();
+        let time = self.next_time();
+
+        self.send(
+            pointer,
+            2,
+            &[
+                Arg::Uint(time),
+                Arg::Fixed(position.0),
+                Arg::Fixed(position.1),
+            ],
+        );
+        self.pointer_frame();
+    }
+
+    pub fn pointer_button(&mut self, button: u32, pressed: bool) {
+        let pointer = self.pointer.unwrap();
+        let serial = self.next_serial();
+        let time = self.next_time();
+
+        self.send(
+            pointer,
+            3,
+            &[
+                Arg::Uint(serial),
+                Arg::Uint(time),
+                Arg::Uint(button),
+                Arg::Uint(pressed as u32),
+            ],
+        );
+        self.pointer_frame();
+    }
+
+    /// Vertical scroll, negative value is scroll up
+    pub fn pointer_scroll(&mut self, value: f64) {
+        let pointer = self.pointer.unwrap();
+        let time = self.next_time();
+
+        self.send(
+            pointer,
+            4,
+            &[Arg::Uint(time), Arg::Uint(0), Arg::Fixed(value)],
+        );
+        self.pointer_frame();
+    }
+
+    fn pointer_frame(&mut self) {
+        let pointer = self.pointer.unwrap();
+        self.send(pointer, 5, &[]);
+    }
+
+    /// Press and release key (evdev code), keyboard focus follows the pointer
+    pub fn key(&mut self, key: u32) {
+        let keyboard = self.keyboard_object.unwrap();
+        let surface = self.focus.unwrap_or_else(|| self.overlay_surface(0));
+        if self.keyboard_focus != Some(surface) {
+            let serial = self.next_serial();
+            self.keyboard_focus = Some(surface);
+            self.send(
+                keyboard,
+                1,
+                &[Arg::Uint(serial), Arg::Uint(surface), Arg::Array(&[])],
+            );
+        }
+
+        for state in [1, 0] {
+            let serial = self.next_serial();
+            let time = self.next_time();
+            self.send(
+                keyboard,
+                3,
+                &[
+                    Arg::Uint(serial),
+                    Arg::Uint(time),
+                    Arg::Uint(key),
+                    Arg::Uint(state),
+                ],
+            );
+        }
+    }
+
+    /// Handle requests until wlrune disconnects and wait for its exit
+    pub fn finish(&mut self) -> Outcome {
+        while self.dispatch() {}
+
+        let mut child = self.child.take().unwrap();
+        let started = Instant::now();
+        while child.try_wait().unwrap().is_none() {
+            if started.elapsed() > TIMEOUT {
+                child.kill().ok();
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let output = child.wait_with_output().unwrap();
+        Outcome {
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+
+    /// Samples of recorded pattern, `None` if it wasn't saved
+    pub fn pattern(&self, name: &str) -> Option<Vec<Vec<(f64, f64)>>> {
+        let path = self
+            .dir
+            .join("data")
+            .join("wlrune")
+            .join("patterns")
+            .join(name);
+        let content = fs::read_to_string(path).ok()?;
+
+        let samples = content
+            .split("\n\n")
+            .map(|sample| {
+                sample
+                    .lines()
+                    .map(|line| {
+                        let (x, y) = line.split_once(' ').unwrap();
+                        (x.parse().unwrap(), y.parse().unwrap())
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Some(samples)
+    }
+
+    fn overlay_surface(&self, output: usize) -> u32 {
+        let (&id, _) = self
+            .surfaces
+            .iter()
+            .find(|(_, surface)| surface.output == Some(output) && surface.mapped)
+            .expect("overlay of output isn't mapped");
+        id
+    }
+
+    fn next_serial(&mut self) -> u32 {
+        self.serial += 1;
+        self.serial
+    }
+
+    fn next_time(&mut self) -> u32 {
+        self.time += 16;
+        self.time
+    }
+
+    /// Read and handle requests, `false` when client disconnected
+    fn dispatch(&mut self) -> bool {
+        let mut buf = [0; 4096];
+        let count = match self.stream.read(&mut buf) {
+            Ok(count) => count,
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                self.fail("wlrune didn't respond in time")
+            }
+            Err(err) if err.kind() == ErrorKind::ConnectionReset => 0,
+            Err(err) => panic!("read failed: {err}"),
+        };
+        if count == 0 {
+            return false;
+        }
+        self.received.extend_from_slice(&buf[..count]);
+
+        // file descriptors of shm pools come as ancillary data, they are dropped by read
+        while self.received.len() >= 8 {
+            let object = u32::from_ne_bytes(self.received[0..4].try_into().unwrap());
+            let header = u32::from_ne_bytes(self.received[4..8].try_into().unwrap());
+            let size = (header >> 16) as usize;
+            if self.received.len() < size {
+                break;
+            }
+
+            let message = self.received.drain(..size).collect::<Vec<_>>();
+            self.handle(
+                object,
+                (header & 0xffff) as u16,
+                ArgReader::new(&message[8..]),
+            );
+        }
+
+        true
+    }
+
+    fn handle(&mut self, id: u32, opcode: u16, mut args: ArgReader) {
+        let Some(&(object, version)) = self.objects.get(&id) else {
+            return;
+        };
+
+        match (object, opcode) {
+            // sync
+            (Object::Display, 0) => {
+                let callback = args.uint();
+                self.send(callback, 0, &[Arg::Uint(0)]);
+                self.send(1, 1, &[Arg::Uint(callback)]);
+            }
+            // get_registry
+            (Object::Display, 1) => {
+                let registry = args.uint();
+                self.objects.insert(registry, (Object::Registry, 1));
+                for (name, global) in self.globals().into_iter().enumerate() {
+                    let (interface, version) = global.interface();
+                    self.send(
+                        registry,
+                        0,
+                        &[
+                            Arg::Uint(name as u32),
+                            Arg::Str(interface),
+                            Arg::Uint(version),
+                        ],
+                    );
+                }
+            }
+            // bind
+            (Object::Registry, 0) => {
+                let name = args.uint() as usize;
+                let _interface = args.string();
+                let version = args.uint();
+                let id = args.uint();
+                let global = self.globals()[name];
+                self.objects.insert(id, (Object::Global(global), version));
+                self.bound(global, id, version);
+            }
+            // create_surface
+            (Object::Global(Global::Compositor), 0) => {
+                let surface = args.uint();
+                self.objects.insert(surface, (Object::Surface, version));
+                self.surfaces.insert(surface, Surface::default());
+            }
+            // create_pool
+            (Object::Global(Global::Shm), 0) => {
+                self.objects.insert(args.uint(), (Object::ShmPool, version));
+            }
+            // create_region, create_buffer, frame
+            (Object::Global(Global::Compositor), 1)
+            | (Object::ShmPool, 0)
+            | (Object::Surface, 3) => {
+                self.objects.insert(args.uint(), (Object::Other, version));
+            }
+            // attach
+            (Object::Surface, 1) => {
+                let buffer = args.uint();
+                self.surfaces.get_mut(&id).unwrap().buffer_attached = buffer != 0;
+            }
+            // commit
+            (Object::Surface, 6) => self.commit(id),
+            // get_layer_surface
+            (Object::Global(Global::LayerShell), 0) => {
+                let layer_surface = args.uint();
+                let surface = args.uint();
+                let output = args.uint();
+                let output = match self.objects.get(&output) {
+                    Some((Object::Global(Global::Output(index)), _)) => *index,
+                    _ => 0,
+                };
+
+                self.objects
+                    .insert(layer_surface, (Object::LayerSurface { surface }, version));
+                let size = self.outputs[output].size;
+                let surface = self.surfaces.get_mut(&surface).unwrap();
+                surface.output = Some(output);
+                surface.size = size;
+            }
+            // set_size, zero means size of the output
+            (Object::LayerSurface { surface }, 0) => {
+                let (width, height) = (args.int(), args.int());
+                let surface = self.surfaces.get_mut(&surface).unwrap();
+                if width > 0 {
+                    surface.size.0 = width;
+                }
+                if height > 0 {
+                    surface.size.1 = height;
+                }
+            }
+            // get_pointer
+            (Object::Global(Global::Seat), 0) => {
+                let pointer = args.uint();
+                self.objects.insert(pointer, (Object::Other, version));
+                self.pointer = Some(pointer);
+            }
+            // get_keyboard
+            (Object::Global(Global::Seat), 1) => {
+                let keyboard = args.uint();
+                self.objects.insert(keyboard, (Object::Other, version));
+                self.keyboard_object = Some(keyboard);
+                self.send_keymap(keyboard);
+                if version >= 4 {
+                    self.send(keyboard, 5, &[Arg::Int(0), Arg::Int(0)]);
+                }
+            }
+            // get_xdg_output
+            (Object::Global(Global::XdgOutputManager), 1) => {
+                let xdg_output = args.uint();
+                let output = args.uint();
+                let Some(&(Object::Global(Global::Output(index)), output_version)) =
+                    self.objects.get(&output)
+                else {
+                    return;
+                };
+
+                self.objects.insert(xdg_output, (Object::Other, version));
+                let Output { position, size, .. } = self.outputs[index];
+                self.send(xdg_output, 0, &[Arg::Int(position.0), Arg::Int(position.1)]);
+                self.send(xdg_output, 1, &[Arg::Int(size.0), Arg::Int(size.1)]);
+                if version >= 3 && output_version >= 2 {
+                    self.send(output, 2, &[]);
+                } else {
+                    self.send(xdg_output, 2, &[]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn globals(&self) -> Vec<Global> {
+        let mut globals = vec![
+            Global::Compositor,
+            Global::Shm,
+            Global::LayerShell,
+            Global::Seat,
+            Global::XdgOutputManager,
+        ];
+        globals.extend((0..self.outputs.len()).map(Global::Output));
+        globals
+    }
+
+    /// Initial events of bound global
+    fn bound(&mut self, global: Global, id: u32, version: u32) {
+        match global {
+            Global::Shm => {
+                // argb8888 and xrgb8888
+                self.send(id, 0, &[Arg::Uint(0)]);
+                self.send(id, 0, &[Arg::Uint(1)]);
+            }
+            Global::Seat => {
+                let capabilities = if self.keyboard { 0b11 } else { 0b01 };
+                self.send(id, 0, &[Arg::Uint(capabilities)]);
+                if version >= 2 {
+                    self.send(id, 1, &[Arg::Str("seat0")]);
+                }
+            }
+            Global::Output(index) => {
+                let Output {
+                    name,
+                    position,
+                    size,
+                } = self.outputs[index];
+                self.send(
+                    id,
+                    0,
+                    &[
+                        Arg::Int(position.0),
+                        Arg::Int(position.1),
+                        Arg::Int(0),
+                        Arg::Int(0),
+                        Arg::Int(0),
+                        Arg::Str("wlrune"),
+                        Arg::Str("test"),
+                        Arg::Int(0),
+                    ],
+                );
+                // current and preferred mode
+                self.send(
+                    id,
+                    1,
+                    &[
+                        Arg::Uint(0b11),
+                        Arg::Int(size.0),
+                        Arg::Int(size.1),
+                        Arg::Int(60000),
+                    ],
+                );
+                if version >= 2 {
+                    self.send(id, 3, &[Arg::Int(1)]);
+                }
+                if version >= 4 {
+                    self.send(id, 4, &[Arg::Str(name)]);
+                }
+                if version >= 2 {
+                    self.send(id, 2, &[]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The first commit of layer surface is answered with configure, commit with
+    /// buffer after it maps the surface
+    fn commit(&mut self, id: u32) {
+        let layer_surface = self.objects.iter().find_map(|(layer_id, (object, _))| {
+            (*object == Object::LayerSurface { surface: id }).then_some(*layer_id)
+        });
+        let Some(layer_surface) = layer_surface else {
+            return;
+        };
+
+        let surface = self.surfaces.get_mut(&id).unwrap();
+        if surface.configured {
+            if surface.mapped {
+                self.redraws += 1;
+            }
+            surface.mapped |= surface.buffer_attached;
+            return;
+        }
+
+        surface.configured = true;
+        let (width, height) = surface.size;
+        let serial = self.next_serial();
+        self.send(
+            layer_surface,
+            0,
+            &[
+                Arg::Uint(serial),
+                Arg::Uint(width as u32),
+                Arg::Uint(height as u32),
+            ],
+        );
+    }
+
+    fn send_keymap(&mut self, keyboard: u32) {
+        let path = self.dir.join("keymap.xkb");
+        let mut keymap = KEYMAP.as_bytes().to_vec();
+        keymap.push(0);
+        fs::write(&path, &keymap).unwrap();
+        let file = File::open(&path).unwrap();
+
+        // xkb_v1 format, the file is passed as ancillary data
+        let message = encode(keyboard, 0, &[Arg::Uint(1), Arg::Uint(keymap.len() as u32)]);
+        let fds = [file.as_fd()];
+        let mut space = [0; rustix::cmsg_space!(ScmRights(1))];
+        let mut control = SendAncillaryBuffer::new(&mut space);
+        control.push(SendAncillaryMessage::ScmRights(&fds));
+        sendmsg(
+            &self.stream,
+            &[IoSlice::new(&message)],
+            &mut control,
+            SendFlags::empty(),
+        )
+        .unwrap();
+    }
+
+    fn send(&mut self, object: u32, opcode: u16, args: &[Arg]) {
+        // client may be already gone, that's found out on the next read
+        let _ = self.stream.write_all(&encode(object, opcode, args));
+    }
+
+    fn fail(&mut self, message: &str) -> ! {
+        let stderr = match self.child.take() {
+            Some(mut child) => {
+                child.kill().ok();
+                let output = child.wait_with_output().unwrap();
+                String::from_utf8_lossy(&output.stderr).into_owned()
+            }
+            None => String::new(),
+        };
+        panic!("{message}, stderr:\n{stderr}");
+    }
+}
+
+impl Drop for Compositor {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+enum Arg<'a> {
+    Int(i32),
+    Uint(u32),
+    /// 24.8 fixed point number
+    Fixed(f64),
+    Str(&'a str),
+    Array(&'a [u8]),
+}
+
+fn encode(object: u32, opcode: u16, args: &[Arg]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for arg in args {
+        match arg {
+            Arg::Int(value) => body.extend_from_slice(&value.to_ne_bytes()),
+            Arg::Uint(value) => body.extend_from_slice(&value.to_ne_bytes()),
+            Arg::Fixed(value) => body.extend_from_slice(&((value * 256.0) as i32).to_ne_bytes()),
+            Arg::Str(text) => {
+                // length includes terminating zero
+                body.extend_from_slice(&(text.len() as u32 + 1).to_ne_bytes());
+                body.extend_from_slice(text.as_bytes());
+                body.push(0);
+                pad(&mut body);
+            }
+            Arg::Array(bytes) => {
+                body.extend_from_slice(&(bytes.len() as u32).to_ne_bytes());
+                body.extend_from_slice(bytes);
+                pad(&mut body);
+            }
+        }
+    }
+
+    let size = (8 + body.len()) as u32;
+    let mut message = Vec::with_capacity(size as usize);
+    message.extend_from_slice(&object.to_ne_bytes());
+    message.extend_from_slice(&((size << 16) | opcode as u32).to_ne_bytes());
+    message.extend_from_slice(&body);
+    message
+}
+
+/// Arguments are aligned to 32 bits
+fn pad(body: &mut Vec<u8>) {
+    body.resize(body.len().next_multiple_of(4), 0);
+}
+
+struct ArgReader<'a> {
+    body: &'a [u8],
+}
+
+impl<'a> ArgReader<'a> {
+    fn new(body: &'a [u8]) -> Self {
+        ArgReader { body }
+    }
+
+    fn uint(&mut self) -> u32 {
+        let (value, rest) = self.body.split_at(4);
+        self.body = rest;
+        u32::from_ne_bytes(value.try_into().unwrap())
+    }
+
+    fn int(&mut self) -> i32 {
+        self.uint() as i32
+    }
+
+    fn string(&mut self) -> String {
+        let len = self.uint() as usize;
+        let padded = len.div_ceil(4) * 4;
+        let (text, rest) = self.body.split_at(padded);
+        self.body = rest;
+        String::from_utf8_lossy(&text[..len.saturating_sub(1)]).into_owned()
+    }
+}
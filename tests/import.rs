@@ -0,0 +1,144 @@
+//! Importers of other gesture tools run on checked in fixtures
+//!
+//! `easystroke-actions-0.5.6` is named after the actions file of easystroke 0.5.6, the
+//! version whose archive layout is still read and written by later releases. It's written
+//! by hand in that boost text archive layout (strokes of action, then action and its
+//! name), with points normalized to the unit square and timestamps as easystroke stores
+//! them, it isn't saved by easystroke itself.
+
+mod session;
+
+use session::Session;
+
+const CONFIG: &str = "commands: []\n";
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn samples(content: &str) -> Vec<usize> {
+    content
+        .split("\n\n")
+        .map(|sample| sample.lines().count())
+        .collect()
+}
+
+#[test]
+fn easystroke_actions_are_imported_with_commands() {
+    let session = Session::new(CONFIG);
+    let actions = fixture("easystroke-actions-0.5.6");
+
+    let outcome = session.run(&["import", "easystroke", &actions]);
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+
+    // both strokes of the action become samples of one pattern
+    let up = session.read_pattern("up").unwrap();
+    assert_eq!(samples(&up), vec![12, 10]);
+    assert!(up.starts_with("0.5 1\n0.502694 0.909091\n"), "{up}");
+
+    assert_eq!(
+        samples(&session.read_pattern("close-tab").unwrap()),
+        vec![14]
+    );
+    assert_eq!(samples(&session.read_pattern("reload").unwrap()), vec![16]);
+
+    // SendKey and SendText actions have no command
+    assert!(
+        outcome
+            .stdout
+            .contains("- pattern: up\n  command: firefox --new-window\n"),
+        "{}",
+        outcome.stdout
+    );
+    assert!(
+        outcome
+            .stdout
+            .contains("- pattern: reload\n  command: xdotool key F5\n"),
+        "{}",
+        outcome.stdout
+    );
+    assert!(!outcome.stdout.contains("hello"), "{}", outcome.stdout);
+}
+
+#[test]
+fn easystroke_action_with_dot_name_is_auto_named() {
+    let session = Session::new(CONFIG);
+    let actions = fixture("easystroke-actions-0.5.6");
+
+    let outcome = session.run(&[
+        "import",
+        "easystroke",
+        &actions,
+        "--on-conflict",
+        "overwrite",
+    ]);
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert_eq!(
+        samples(&session.read_pattern("easystroke-4").unwrap()),
+        vec![8]
+    );
+    assert!(session.patterns_dir().is_dir());
+}
+
+#[test]
+fn non_archive_file_is_rejected() {
+    let session = Session::new(CONFIG);
+    let samples = fixture("dollar_one_import.json");
+
+    let outcome = session.run(&["import", "easystroke", &samples]);
+    assert!(!outcome.status.success());
+    assert!(
+        outcome.stderr.contains("is not easystroke actions file"),
+        "{}",
+        outcome.stderr
+    );
+}
+
+#[test]
+fn dollar_one_samples_are_grouped_by_name() {
+    let session = Session::new(CONFIG);
+    let samples_path = fixture("dollar_one_import.json");
+
+    let outcome = session.run(&["import", "one-dollar", &samples_path]);
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+
+    let check = session.read_pattern("check").unwrap();
+    assert_eq!(samples(&check), vec![10, 10]);
+    assert!(check.starts_with("10 50\n20 60\n"), "{check}");
+
+    // object points with capitalized keys
+    let caret = session.read_pattern("caret").unwrap();
+    assert!(caret.starts_with("0 100\n12.5 75\n"), "{caret}");
+
+    // slash and spaces can't be part of file name
+    assert_eq!(
+        samples(&session.read_pattern("right-arrow-2").unwrap()),
+        vec![7]
+    );
+}
+
+#[test]
+fn dollar_one_blank_name_doesnt_abort_import() {
+    let session = Session::new(CONFIG);
+    session.write_pattern("check", "1 1\n2 2");
+    let samples_path = fixture("dollar_one_import.json");
+
+    let outcome = session.run(&[
+        "import",
+        "one-dollar",
+        &samples_path,
+        "--on-conflict",
+        "overwrite",
+    ]);
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert!(
+        outcome.stderr.contains("saved as sample-5"),
+        "{}",
+        outcome.stderr
+    );
+    assert_eq!(samples(&session.read_pattern("sample-5").unwrap()), vec![6]);
+    assert_eq!(
+        samples(&session.read_pattern("check").unwrap()),
+        vec![10, 10]
+    );
+}
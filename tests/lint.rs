@@ -0,0 +1,59 @@
+//! Diagnostics of `wlrune pattern lint` on prepared patterns directory
+
+mod session;
+
+use session::Session;
+
+#[test]
+fn malformed_pattern_file_is_reported() {
+    let session = Session::new("commands:\n  - pattern: down\n    command: \"true\"\n");
+    session.write_pattern("down", "50 0\n50 25\n50 50\n50 75\n50 100");
+    session.write_pattern("notes", "50 0\n50 oops\n");
+
+    let outcome = session.run(&["pattern", "lint"]);
+    assert_eq!(outcome.status.code(), Some(1), "{}", outcome.stderr);
+    assert!(
+        outcome
+            .stderr
+            .contains("malformed: pattern notes can't be loaded")
+            && outcome.stderr.contains("line 2"),
+        "{}",
+        outcome.stderr
+    );
+    assert!(
+        outcome.stderr.contains("found 1 issue(s) in 2 pattern(s)"),
+        "{}",
+        outcome.stderr
+    );
+}
+
+#[test]
+fn missing_pattern_is_reported() {
+    let session = Session::new("commands:\n  - pattern: up\n    command: \"true\"\n");
+    session.write_pattern("down", "50 0\n50 25\n50 50\n50 75\n50 100");
+
+    let outcome = session.run(&["pattern", "lint"]);
+    assert!(!outcome.status.success());
+    assert!(
+        outcome
+            .stderr
+            .contains("missing: pattern up used in config but not recorded"),
+        "{}",
+        outcome.stderr
+    );
+}
+
+#[test]
+fn distinct_patterns_pass() {
+    let session = Session::new("commands:\n  - pattern: down\n    command: \"true\"\n");
+    session.write_pattern("down", "50 0\n50 25\n50 50\n50 75\n50 100");
+    session.write_pattern("right", "0 50\n25 50\n50 50\n75 50\n100 50");
+
+    let outcome = session.run(&["pattern", "lint"]);
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert!(
+        outcome.stderr.contains("no issues found in 2 pattern(s)"),
+        "{}",
+        outcome.stderr
+    );
+}
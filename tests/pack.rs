@@ -0,0 +1,174 @@
+//! Pattern packs exported and imported between isolated sessions
+
+mod session;
+
+use session::Session;
+
+const CONFIG: &str = "commands: []\n";
+
+#[test]
+fn export_skips_malformed_pattern_file() {
+    let session = Session::new(CONFIG);
+    session.write_pattern("left", "10 10\n20 10\n30 10\n");
+    session.write_pattern("notes", "these aren't points\n");
+
+    let outcome = session.run(&["pack", "export", "--output", "pack.yaml"]);
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert!(
+        outcome.stderr.contains("pattern notes skipped") && outcome.stderr.contains("line 1"),
+        "{}",
+        outcome.stderr
+    );
+
+    let pack = std::fs::read_to_string(session.path("pack.yaml")).unwrap();
+    assert!(pack.contains("name: left"), "{pack}");
+    assert!(!pack.contains("notes"), "{pack}");
+}
+
+const LEFT: &str = "30 10\n20 10\n10 10\n\n31 12\n20 11\n9 10";
+const RIGHT: &str = "10 10\n20 10\n30 10";
+
+/// Pack exported from the new session with given gesture files
+fn export(patterns: &[(&str, &str)]) -> String {
+    let session = Session::new(CONFIG);
+    for (name, content) in patterns {
+        session.write_pattern(name, content);
+    }
+
+    let outcome = session.run(&["pack", "export", "--output", "pack.yaml"]);
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+
+    std::fs::read_to_string(session.path("pack.yaml")).unwrap()
+}
+
+fn import(session: &Session, pack: &str, on_conflict: &str) -> session::Outcome {
+    std::fs::write(session.path("pack.yaml"), pack).unwrap();
+    session.run(&["pack", "import", "pack.yaml", "--on-conflict", on_conflict])
+}
+
+#[test]
+fn exported_patterns_are_imported_unchanged() {
+    let pack = export(&[("left", LEFT), ("right", RIGHT)]);
+
+    let session = Session::new(CONFIG);
+    let outcome = import(&session, &pack, "skip");
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert_eq!(session.read_pattern("left").as_deref(), Some(LEFT));
+    assert_eq!(session.read_pattern("right").as_deref(), Some(RIGHT));
+}
+
+#[test]
+fn skip_keeps_existing_pattern() {
+    let pack = export(&[("left", LEFT)]);
+
+    let session = Session::new(CONFIG);
+    session.write_pattern("left", RIGHT);
+    let outcome = import(&session, &pack, "skip");
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert_eq!(session.read_pattern("left").as_deref(), Some(RIGHT));
+    assert_eq!(session.read_pattern("left-2"), None);
+}
+
+#[test]
+fn overwrite_replaces_existing_pattern() {
+    let pack = export(&[("left", LEFT)]);
+
+    let session = Session::new(CONFIG);
+    session.write_pattern("left", RIGHT);
+    let outcome = import(&session, &pack, "overwrite");
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert_eq!(session.read_pattern("left").as_deref(), Some(LEFT));
+}
+
+#[test]
+fn rename_saves_pattern_under_free_name() {
+    let pack = export(&[("left", LEFT)]);
+
+    let session = Session::new(CONFIG);
+    session.write_pattern("left", RIGHT);
+    session.write_pattern("left-2", RIGHT);
+    let outcome = import(&session, &pack, "rename");
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert_eq!(session.read_pattern("left").as_deref(), Some(RIGHT));
+    assert_eq!(session.read_pattern("left-2").as_deref(), Some(RIGHT));
+    assert_eq!(session.read_pattern("left-3").as_deref(), Some(LEFT));
+}
+
+#[test]
+fn unchanged_pattern_isnt_renamed() {
+    let pack = export(&[("left", LEFT)]);
+
+    let session = Session::new(CONFIG);
+    session.write_pattern("left", LEFT);
+    let outcome = import(&session, &pack, "rename");
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert_eq!(session.read_pattern("left-2"), None);
+}
+
+#[test]
+fn checksum_mismatch_rejects_whole_pack() {
+    let pack = export(&[("left", LEFT), ("right", RIGHT)]);
+    // point of `left` modified after export
+    let pack = pack.replacen("- 30.0", "- 35.0", 1);
+
+    let session = Session::new(CONFIG);
+    let outcome = import(&session, &pack, "overwrite");
+    assert!(!outcome.status.success());
+    assert!(
+        outcome.stderr.contains("checksum mismatch"),
+        "{}",
+        outcome.stderr
+    );
+    assert_eq!(session.read_pattern("left"), None);
+    assert_eq!(session.read_pattern("right"), None);
+}
+
+#[test]
+fn dot_names_are_rejected() {
+    let pack = export(&[("left", LEFT)]);
+
+    for name in ["..", ".", ".hidden"] {
+        let session = Session::new(CONFIG);
+        let outcome = import(
+            &session,
+            &pack.replace("name: left", &format!("name: '{name}'")),
+            "overwrite",
+        );
+        assert!(!outcome.status.success(), "{name} accepted");
+        assert!(
+            outcome.stderr.contains("invalid pattern name"),
+            "{}",
+            outcome.stderr
+        );
+    }
+}
+
+#[test]
+fn hidden_files_arent_exported() {
+    let pack = export(&[("left", LEFT), (".left.swp", "garbage")]);
+    assert!(!pack.contains("swp"), "{pack}");
+}
+
+#[test]
+fn version_one_pack_is_imported() {
+    let pack = "version: 1\npatterns:\n- name: right\n  checksum: 27275f4546b24bf2\n  path:\n  - [10.0, 10.0]\n  - [20.0, 10.0]\n  - [30.0, 10.0]\n";
+
+    let session = Session::new(CONFIG);
+    let outcome = import(&session, pack, "skip");
+    assert!(outcome.status.success(), "{}", outcome.stderr);
+    assert_eq!(session.read_pattern("right").as_deref(), Some(RIGHT));
+}
+
+#[test]
+fn newer_pack_version_is_reported() {
+    let pack = export(&[("left", LEFT)]).replace("version: 2", "version: 3");
+
+    let session = Session::new(CONFIG);
+    let outcome = import(&session, &pack, "skip");
+    assert!(!outcome.status.success());
+    assert!(
+        outcome.stderr.contains("unsupported pack version 3"),
+        "{}",
+        outcome.stderr
+    );
+}
@@ -0,0 +1,92 @@
+//! Isolated home for running subcommands that don't need compositor
+//!
+//! Every session gets its own `$HOME` and `$XDG_DATA_HOME` in the temp directory,
+//! so recorded patterns of the user are never touched.
+
+// shared by several test crates, each one uses only part of it
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{Command, ExitStatus, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static SESSION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Session {
+    dir: PathBuf,
+}
+
+pub struct Outcome {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Session {
+    pub fn new(config: &str) -> Self {
+        let dir = env::temp_dir().join(format!(
+            "wlrune-cli-test-{}-{}",
+            std::process::id(),
+            SESSION_COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("data").join("wlrune").join("patterns")).unwrap();
+        fs::write(dir.join("config.yaml"), config).unwrap();
+
+        Session { dir }
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    pub fn patterns_dir(&self) -> PathBuf {
+        self.dir.join("data").join("wlrune").join("patterns")
+    }
+
+    /// Write gesture file as is, content doesn't have to be valid
+    pub fn write_pattern(&self, name: &str, content: &str) {
+        fs::write(self.patterns_dir().join(name), content).unwrap();
+    }
+
+    pub fn read_pattern(&self, name: &str) -> Option<String> {
+        fs::read_to_string(self.patterns_dir().join(name)).ok()
+    }
+
+    pub fn remove_pattern(&self, name: &str) {
+        fs::remove_file(self.patterns_dir().join(name)).unwrap();
+    }
+
+    pub fn run(&self, args: &[&str]) -> Outcome {
+        let output = Command::new(env!("CARGO_BIN_EXE_wlrune"))
+            .arg("--config")
+            .arg(self.dir.join("config.yaml"))
+            .args(args)
+            .current_dir(&self.dir)
+            .env("HOME", &self.dir)
+            .env("XDG_DATA_HOME", self.dir.join("data"))
+            // there is no compositor socket in it
+            .env("XDG_RUNTIME_DIR", &self.dir)
+            .env_remove("WAYLAND_DISPLAY")
+            .env_remove("WAYLAND_SOCKET")
+            .env_remove("DBUS_SESSION_BUS_ADDRESS")
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+
+        Outcome {
+            status: output.status,
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}